@@ -0,0 +1,84 @@
+//! Times a headless battle between two full armies of knights.
+//!
+//! `cargo run --release --example battle_bench [units per squad]`
+
+use std::time::{Duration, Instant};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use tower_quest::{
+    battle::{
+        layout::{ARENA_HEIGHT, ARENA_WIDTH, MAX_COLUMNS, MAX_ROWS},
+        sim::{BattleResult, BattleSimPlugin},
+        units::{
+            squad::{Squad, SquadBundle, SquadCount, UnitType},
+            Team,
+        },
+    },
+    rng::RunRng,
+};
+
+/// A frame at 60 FPS.
+const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+/// Frames to time, the battle is usually decided long before.
+const MAX_FRAMES: usize = 3600;
+
+#[derive(Resource)]
+struct SquadSize(usize);
+
+fn spawn_armies(mut commands: Commands, size: Res<SquadSize>) {
+    let column_width = ARENA_WIDTH / 2.0 / (MAX_COLUMNS + 1) as f32;
+    let row_height = ARENA_HEIGHT / MAX_ROWS as f32;
+
+    for (team, side) in [(Team::Player, -1.0), (Team::Enemy, 1.0)] {
+        for column in 0..MAX_COLUMNS {
+            for row in 0..MAX_ROWS {
+                let x = side * (column + 1) as f32 * column_width;
+                let y = (row as f32 - (MAX_ROWS - 1) as f32 / 2.0) * row_height;
+
+                commands.spawn((
+                    SquadBundle {
+                        count: SquadCount(size.0),
+                        formation: default(),
+                        squad: Squad,
+                        unit: UnitType::new("knight"),
+                    },
+                    team.clone(),
+                    TransformBundle::from_transform(Transform::from_xyz(x, y, 0.0)),
+                ));
+            }
+        }
+    }
+}
+
+fn main() {
+    let size = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(100);
+
+    let mut app = App::new();
+
+    app.add_plugins((MinimalPlugins, BattleSimPlugin))
+        .insert_resource(RunRng::new(0))
+        .insert_resource(SquadSize(size))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_BUDGET))
+        .add_systems(Startup, spawn_armies);
+
+    let mut frames = Vec::new();
+
+    while frames.len() < MAX_FRAMES && !app.world.contains_resource::<BattleResult>() {
+        let start = Instant::now();
+        app.update();
+        frames.push(start.elapsed());
+    }
+
+    let units = size * MAX_COLUMNS * MAX_ROWS;
+    let total = frames.iter().sum::<Duration>();
+    let slowest = frames.iter().max().copied().unwrap_or_default();
+    let over_budget = frames.iter().filter(|frame| **frame > FRAME_BUDGET).count();
+
+    println!("{} v {} knights, {} frames", units, units, frames.len());
+    println!("mean frame: {:?}", total / frames.len().max(1) as u32);
+    println!("slowest frame: {:?}", slowest);
+    println!("frames over {:?}: {}", FRAME_BUDGET, over_budget);
+}
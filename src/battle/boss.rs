@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::Floor;
+
+use super::{
+    layout::ARENA_WIDTH,
+    units::{
+        catalog::UnitDefinitions,
+        orders::SquadOrders,
+        squad::{SquadBundle, SquadCount, UnitType},
+        Team,
+    },
+};
+
+/// Every this many floors the enemy is led by a boss.
+pub const BOSS_FLOOR_INTERVAL: usize = 5;
+/// Distance from the enemy's edge of the arena the boss starts at.
+const BOSS_EDGE_DISTANCE: f32 = 60.0;
+
+/// Squad holding the boss of a boss floor, only around for that battle.
+#[derive(Component)]
+pub struct BossSquad;
+
+pub fn is_boss_floor(floor: usize) -> bool {
+    floor > 0 && floor % BOSS_FLOOR_INTERVAL == 0
+}
+
+/// Bosses take turns, in order of their unit ids.
+fn boss_for_floor(definitions: &UnitDefinitions, floor: usize) -> Option<UnitType> {
+    let mut bosses = definitions
+        .catalog()?
+        .0
+        .iter()
+        .filter(|(_, definition)| definition.boss.is_some())
+        .map(|(unit, _)| unit.clone())
+        .collect::<Vec<_>>();
+
+    if bosses.is_empty() {
+        return None;
+    }
+
+    bosses.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let index = (floor / BOSS_FLOOR_INTERVAL).saturating_sub(1) % bosses.len();
+
+    Some(bosses.swap_remove(index))
+}
+
+pub fn spawn_boss(
+    mut commands: Commands,
+    floor: Res<Floor>,
+    definitions: UnitDefinitions,
+    existing: Query<(), With<BossSquad>>,
+) {
+    if !is_boss_floor(floor.0) || !existing.is_empty() {
+        return;
+    }
+
+    let unit = match boss_for_floor(&definitions, floor.0) {
+        Some(unit) => unit,
+        None => {
+            error!("No bosses in the unit catalog");
+            return;
+        }
+    };
+
+    info!("Floor {} is guarded by {:?}", floor.0, unit);
+
+    commands.spawn((
+        BossSquad,
+        SquadBundle {
+            unit,
+            count: SquadCount(1),
+            ..default()
+        },
+        Team::Enemy,
+        SquadOrders::default(),
+        TransformBundle::from_transform(Transform::from_xyz(
+            ARENA_WIDTH / 2.0 - BOSS_EDGE_DISTANCE,
+            0.0,
+            0.0,
+        )),
+        VisibilityBundle::default(),
+    ));
+}
+
+pub fn despawn_boss(mut commands: Commands, squads: Query<Entity, With<BossSquad>>) {
+    for ent in squads.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -2,22 +2,30 @@ use bevy::prelude::*;
 use bevy_round_ui::prelude::{RoundUiBorder, RoundUiMaterial};
 
 use crate::{
+    difficulty::Difficulty,
     menu::{
         button::{self, ButtonAction},
         colors, spawn_button,
     },
+    rng::RunRng,
     Floor,
 };
 
+use super::replay::ReplayToPlay;
+
 #[derive(Component)]
 pub struct ScoreMenu;
 
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_menu(
     mut commands: Commands,
     button_style: Res<button::ButtonStyle>,
     mut materials: ResMut<Assets<RoundUiMaterial>>,
     asset_server: Res<AssetServer>,
     floor: Res<Floor>,
+    rng: Res<RunRng>,
+    difficulty: Res<Difficulty>,
+    replay: Option<Res<ReplayToPlay>>,
 ) {
     let font = asset_server.load("font/vt323.ttf");
 
@@ -87,6 +95,24 @@ pub fn spawn_menu(
                             font: font.clone(),
                         },
                     ));
+
+                    p.spawn(TextBundle::from_section(
+                        format!("Difficulty: {:?}", difficulty.level),
+                        TextStyle {
+                            color: Color::hex(colors::BG_LIGHT).unwrap(),
+                            font_size: 24.0,
+                            font: font.clone(),
+                        },
+                    ));
+
+                    p.spawn(TextBundle::from_section(
+                        format!("Seed: {}", rng.seed()),
+                        TextStyle {
+                            color: Color::hex(colors::BG_LIGHT).unwrap(),
+                            font_size: 24.0,
+                            font: font.clone(),
+                        },
+                    ));
                 });
 
                 spawn_button(
@@ -96,6 +122,17 @@ pub fn spawn_menu(
                     font.clone(),
                     ButtonAction::Start,
                 );
+
+                if replay.is_some() {
+                    spawn_button(
+                        p,
+                        &button_style,
+                        "Watch Replay",
+                        font.clone(),
+                        ButtonAction::WatchReplay,
+                    );
+                }
+
                 spawn_button(p, &button_style, "Quit", font.clone(), ButtonAction::Quit);
             });
         });
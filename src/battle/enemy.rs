@@ -1,13 +1,41 @@
-use rand::Rng;
+use bevy::{ecs::system::SystemParam, prelude::*, reflect::TypePath, utils::HashMap};
+use rand::{seq::SliceRandom, Rng};
 use rand_distr::Normal;
+use serde::{Deserialize, Serialize};
 
-use super::INITIAL_UNITS;
+use crate::{
+    data::Validate,
+    difficulty::{Difficulty, DifficultySettings},
+    rng::{RngStream, RunRng},
+    Floor,
+};
 
-pub fn rand_unit_count(floor: usize) -> usize {
-    let base = INITIAL_UNITS as f32 / 2.0;
-    let base = base + (floor as f32 * 1.5);
+use super::{
+    boss::is_boss_floor,
+    layout::SquadSlot,
+    units::{
+        ai::UnitRole,
+        catalog::UnitDefinitions,
+        formation::{rand_formation, Formation},
+        modifiers::{Modifier, SquadModifiers, Stat},
+        orders::SquadOrders,
+        squad::{SquadBundle, SquadCount, UnitType},
+        Team,
+    },
+};
+
+const CATALOG_PATH: &str = "data/base.armies.ron";
+/// Fraction of the budget left for the rest of the army when a boss leads it.
+const BOSS_FLOOR_BUDGET: f32 = 0.6;
+
+/// Average size of an enemy squad on a floor.
+pub fn mean_unit_count(floor: usize, difficulty: &DifficultySettings) -> f32 {
+    difficulty.enemy_base_units + floor as f32 * difficulty.enemy_units_per_floor
+}
+
+pub fn rand_unit_count(floor: usize, difficulty: &DifficultySettings, rng: &mut impl Rng) -> usize {
+    let base = mean_unit_count(floor, difficulty);
 
-    let mut rng = rand::thread_rng();
     let normal = Normal::new(base, base / 3.0).unwrap();
 
     let count = rng.sample(normal) as usize;
@@ -18,3 +46,425 @@ pub fn rand_unit_count(floor: usize) -> usize {
 
     count
 }
+
+/// What the enemy can spend its budget on, and the kinds of armies it builds.
+#[derive(Asset, TypePath, Deserialize)]
+pub struct ArmyCatalog {
+    /// Points it costs to put one unit of each type in a squad
+    pub costs: HashMap<UnitType, f32>,
+    pub budget: ArmyBudget,
+    /// Bought for squads with whatever is left once the army has its squads
+    #[serde(default)]
+    pub upgrades: Vec<ArmyUpgrade>,
+    pub styles: Vec<ArmyStyle>,
+}
+
+/// Budgets are counted in average sized squads of units that cost 1 point.
+#[derive(Deserialize)]
+pub struct ArmyBudget {
+    pub squads: f32,
+    pub squads_per_floor: f32,
+}
+
+#[derive(Deserialize)]
+pub struct ArmyUpgrade {
+    pub modifier: Modifier,
+    /// Points per unit in the squad
+    pub cost: f32,
+}
+
+#[derive(Deserialize)]
+pub struct ArmyStyle {
+    pub name: String,
+    /// First floor the style can show up on
+    #[serde(default)]
+    pub min_floor: usize,
+    /// Relative chance of each unit type being picked for a squad
+    pub units: HashMap<UnitType, f32>,
+    /// Smallest and largest squads, as fractions of the average squad size on the floor
+    pub squad_size: (f32, f32),
+    #[serde(default)]
+    pub max_squads: Option<usize>,
+    /// Formations squads are put in, any of them if empty
+    #[serde(default)]
+    pub formations: Vec<Formation>,
+}
+
+impl ArmyCatalog {
+    /// Points it costs to put one unit of a type in a squad.
+    /// Units without a cost are priced like a knight.
+    pub fn cost(&self, unit: &UnitType) -> f32 {
+        self.costs.get(unit).copied().unwrap_or(1.0)
+    }
+}
+
+impl Validate for ArmyCatalog {
+    fn validate(&self) -> Result<(), String> {
+        for (unit, cost) in self.costs.iter() {
+            if *cost <= 0.0 {
+                return Err(format!(
+                    "cost of {:?} must be greater than 0, got {}",
+                    unit, cost
+                ));
+            }
+        }
+
+        if self.budget.squads <= 0.0 || self.budget.squads_per_floor < 0.0 {
+            return Err("budget must be greater than 0 and grow with each floor".to_string());
+        }
+
+        for (i, upgrade) in self.upgrades.iter().enumerate() {
+            if upgrade.cost <= 0.0 {
+                return Err(format!(
+                    "upgrade {}: cost must be greater than 0, got {}",
+                    i, upgrade.cost
+                ));
+            }
+
+            // Squad size is what the budget pays for, so it can't come for free
+            if upgrade.modifier.stat == Stat::SquadSize {
+                return Err(format!("upgrade {}: can't modify SquadSize", i));
+            }
+        }
+
+        if !self.styles.iter().any(|style| style.min_floor == 0) {
+            return Err("at least one style must be available from floor 0".to_string());
+        }
+
+        for style in self.styles.iter() {
+            style
+                .validate()
+                .and_then(|_| {
+                    match style
+                        .units
+                        .keys()
+                        .find(|unit| !self.costs.contains_key(*unit))
+                    {
+                        Some(unit) => Err(format!("{:?} has no cost", unit)),
+                        None => Ok(()),
+                    }
+                })
+                .map_err(|e| format!("style \"{}\": {}", style.name, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for ArmyStyle {
+    fn validate(&self) -> Result<(), String> {
+        if !self.units.values().any(|weight| *weight > 0.0) {
+            return Err("at least one unit needs a weight greater than 0".to_string());
+        }
+
+        if self.units.values().any(|weight| *weight < 0.0) {
+            return Err("weights must not be negative".to_string());
+        }
+
+        let (min, max) = self.squad_size;
+
+        if min <= 0.0 || max < min {
+            return Err(format!(
+                "squad_size must be greater than 0 and go from smallest to largest, got ({}, {})",
+                min, max
+            ));
+        }
+
+        if self.max_squads == Some(0) {
+            return Err("max_squads must be at least 1".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+pub struct ArmyCatalogHandle(pub Handle<ArmyCatalog>);
+
+pub fn load_armies(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ArmyCatalogHandle(asset_server.load(CATALOG_PATH)));
+}
+
+#[derive(SystemParam)]
+pub struct ArmyCatalogs<'w> {
+    handle: Option<Res<'w, ArmyCatalogHandle>>,
+    catalogs: Res<'w, Assets<ArmyCatalog>>,
+}
+
+impl<'w> ArmyCatalogs<'w> {
+    pub fn catalog(&self) -> Option<&ArmyCatalog> {
+        self.catalogs.get(&self.handle.as_ref()?.0)
+    }
+}
+
+/// A squad the enemy took as a reward, added to its army on every floor after.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DraftedSquad {
+    pub unit: UnitType,
+    pub count: usize,
+}
+
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DraftedSquads(pub Vec<DraftedSquad>);
+
+/// Set when the enemy needs a new army before the next battle.
+#[derive(Resource, Default)]
+pub struct ArmyPending(pub bool);
+
+/// A squad bought for the enemy's army.
+struct ArmySquad {
+    unit: UnitType,
+    count: usize,
+    formation: Formation,
+    modifiers: Vec<Modifier>,
+}
+
+/// Points the enemy has to spend on a floor.
+fn budget(catalog: &ArmyCatalog, floor: usize, difficulty: &DifficultySettings) -> f32 {
+    let squads = catalog.budget.squads + floor as f32 * catalog.budget.squads_per_floor;
+    let budget = squads * mean_unit_count(floor, difficulty);
+
+    match is_boss_floor(floor) {
+        true => budget * BOSS_FLOOR_BUDGET,
+        false => budget,
+    }
+}
+
+/// Buys squads, then upgrades for them, until the budget or the slots run out.
+fn buy_army(
+    catalog: &ArmyCatalog,
+    style: &ArmyStyle,
+    mut budget: f32,
+    slots: usize,
+    mean_size: f32,
+    rng: &mut impl Rng,
+) -> Vec<ArmySquad> {
+    let mut squads = Vec::new();
+    let max_squads = style.max_squads.unwrap_or(usize::MAX).min(slots);
+    let (min_size, max_size) = style.squad_size;
+    let min_count = (mean_size * min_size).round().max(1.0) as usize;
+
+    let units = style
+        .units
+        .iter()
+        .filter(|(_, weight)| **weight > 0.0)
+        .filter_map(|(unit, weight)| Some((unit, *weight, *catalog.costs.get(unit)?)))
+        .collect::<Vec<_>>();
+
+    while squads.len() < max_squads {
+        // Only units the budget can still fill a squad with
+        let affordable = units
+            .iter()
+            .filter(|(_, _, cost)| cost * min_count as f32 <= budget)
+            .collect::<Vec<_>>();
+
+        let (unit, _, cost) = match affordable.choose_weighted(rng, |(_, weight, _)| *weight) {
+            Ok(unit) => unit,
+            Err(_) => break,
+        };
+
+        let size = rng.gen_range(min_size..=max_size);
+        let count = ((mean_size * size).round() as usize)
+            .max(min_count)
+            .min((budget / cost) as usize);
+
+        budget -= cost * count as f32;
+
+        let formation = match style.formations.choose(rng) {
+            Some(formation) => formation.clone(),
+            None => rand_formation(rng),
+        };
+
+        squads.push(ArmySquad {
+            unit: (*unit).clone(),
+            count,
+            formation,
+            modifiers: Vec::new(),
+        });
+    }
+
+    loop {
+        let affordable = squads
+            .iter()
+            .enumerate()
+            .flat_map(|(i, squad)| {
+                catalog
+                    .upgrades
+                    .iter()
+                    .map(move |upgrade| (i, upgrade, upgrade.cost * squad.count as f32))
+            })
+            .filter(|(_, _, cost)| *cost <= budget)
+            .collect::<Vec<_>>();
+
+        let (i, upgrade, cost) = match affordable.choose(rng) {
+            Some(upgrade) => upgrade,
+            None => break,
+        };
+
+        budget -= cost;
+        squads[*i].modifiers.push(upgrade.modifier.clone());
+    }
+
+    squads
+}
+
+/// Replaces the enemy's squads with a new army bought with the floor's budget.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_army(
+    mut commands: Commands,
+    mut pending: ResMut<ArmyPending>,
+    floor: Res<Floor>,
+    difficulty: Res<Difficulty>,
+    drafted: Res<DraftedSquads>,
+    armies: ArmyCatalogs,
+    definitions: UnitDefinitions,
+    slots: Query<(Entity, &Team, &Transform), With<SquadSlot>>,
+    mut rng: ResMut<RunRng>,
+) {
+    if !pending.0 {
+        return;
+    }
+
+    pending.0 = false;
+
+    let catalog = match armies.catalog() {
+        Some(catalog) => catalog,
+        None => {
+            error!("Army catalog not loaded");
+            return;
+        }
+    };
+
+    let rng = rng.stream(RngStream::Army);
+
+    let styles = catalog
+        .styles
+        .iter()
+        .filter(|style| style.min_floor <= floor.0)
+        .collect::<Vec<_>>();
+
+    let style = match styles.choose(rng) {
+        Some(style) => style,
+        None => return,
+    };
+
+    let mut slots = slots
+        .iter()
+        .filter(|(_, team, _)| **team == Team::Enemy)
+        .map(|(ent, _, transform)| (ent, transform.translation.x))
+        .collect::<Vec<_>>();
+
+    for (ent, _) in slots.iter() {
+        commands
+            .entity(*ent)
+            .remove::<(SquadBundle, SquadModifiers, SquadOrders)>();
+    }
+
+    // Squads the enemy drafted are paid for out of the budget, in the slots there are
+    let mut squads = drafted
+        .0
+        .iter()
+        .take(slots.len())
+        .map(|squad| ArmySquad {
+            unit: squad.unit.clone(),
+            count: squad.count,
+            formation: rand_formation(rng),
+            modifiers: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    let budget = budget(catalog, floor.0, &difficulty.settings);
+    let mut drafted_cost = squads
+        .iter()
+        .map(|squad| catalog.cost(&squad.unit) * squad.count as f32)
+        .sum::<f32>();
+
+    // Drafting can't buy more than the floor allows, the last squads drafted are cut first
+    if drafted_cost > budget {
+        info!(
+            "Drafted squads cost {:.0} over a budget of {:.0}, trimming them",
+            drafted_cost, budget
+        );
+    }
+
+    while drafted_cost > budget {
+        let squad = match squads.last_mut() {
+            Some(squad) => squad,
+            None => break,
+        };
+
+        let unit_cost = catalog.cost(&squad.unit);
+        let excess = ((drafted_cost - budget) / unit_cost).ceil() as usize;
+
+        if excess >= squad.count {
+            drafted_cost -= unit_cost * squad.count as f32;
+            squads.pop();
+        } else {
+            drafted_cost -= unit_cost * excess as f32;
+            squad.count -= excess;
+        }
+    }
+    let open_slots = slots.len().saturating_sub(squads.len());
+    let mean_size = mean_unit_count(floor.0, &difficulty.settings);
+
+    squads.extend(buy_army(
+        catalog,
+        style,
+        (budget - drafted_cost).max(0.0),
+        open_slots,
+        mean_size,
+        rng,
+    ));
+
+    info!(
+        "Enemy army on floor {}: {}, {} squads for {:.0} points",
+        floor.0,
+        style.name,
+        squads.len(),
+        budget
+    );
+
+    // Enemy slots further right are further back
+    slots.shuffle(rng);
+    slots.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    for squad in squads {
+        // Ranged units and healers stay behind the rest
+        let back = match definitions.get(&squad.unit) {
+            Some(definition) => {
+                definition.projectile.is_some() || definition.role == UnitRole::Healer
+            }
+            None => {
+                error!("Unknown unit type: {:?}", squad.unit);
+                continue;
+            }
+        };
+
+        let slot = match back {
+            true => slots.pop(),
+            false => match slots.is_empty() {
+                true => None,
+                false => Some(slots.remove(0)),
+            },
+        };
+
+        let (ent, _) = match slot {
+            Some(slot) => slot,
+            None => {
+                error!("No open slots");
+                break;
+            }
+        };
+
+        commands.entity(ent).insert((
+            SquadBundle {
+                count: SquadCount(squad.count),
+                formation: squad.formation,
+                unit: squad.unit,
+                ..default()
+            },
+            SquadModifiers(squad.modifiers),
+        ));
+    }
+}
@@ -1,12 +1,11 @@
 use bevy::prelude::*;
 
 use crate::{
-    battle::INITIAL_UNITS,
+    difficulty::Difficulty,
     rewards::effects::{AddColumn, AddRow, AddSquad},
 };
 
 use super::{
-    enemy::rand_unit_count,
     units::squad::{Squad, SquadBundle, SquadCount, UnitType},
     Team,
 };
@@ -52,22 +51,16 @@ pub fn init_slots(
     }
 }
 
-pub fn init_units(mut add_squad_writer: EventWriter<AddSquad>) {
-    for team in &[Team::Player, Team::Enemy] {
-        let num_units = match team {
-            Team::Player => INITIAL_UNITS,
-            Team::Enemy => rand_unit_count(1),
-        };
-
-        add_squad_writer.send(AddSquad {
-            team: team.clone(),
-            squad: SquadBundle {
-                unit: UnitType::Knight,
-                count: SquadCount(num_units),
-                ..default()
-            },
-        });
-    }
+/// Gives the player their first squad, the enemy's army is bought by [`super::enemy::generate_army`].
+pub fn init_units(mut add_squad_writer: EventWriter<AddSquad>, difficulty: Res<Difficulty>) {
+    add_squad_writer.send(AddSquad {
+        team: Team::Player,
+        squad: SquadBundle {
+            unit: UnitType::knight(),
+            count: SquadCount(difficulty.settings.initial_units),
+            ..default()
+        },
+    });
 }
 
 const TERRITORY_WIDTH: f32 = (ARENA_WIDTH / 2.0) - (TEAM_GAP / 2.0);
@@ -1,16 +1,21 @@
 use bevy::prelude::*;
 
-use crate::GameState;
+use crate::{data::RonLoader, GameState};
 
 use self::{
     layout::{EnemyUnlockedSlots, FriendlyUnlockedSlots},
     units::Team,
 };
 
+pub mod boss;
 pub mod camera;
 mod defeat;
 pub mod enemy;
 pub mod layout;
+mod orders;
+pub mod replay;
+pub mod sim;
+pub mod terrain;
 pub mod units;
 mod victory;
 
@@ -20,13 +25,30 @@ impl Plugin for BattlePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<FriendlyUnlockedSlots>()
             .init_resource::<EnemyUnlockedSlots>()
-            .add_plugins(units::UnitsPlugin)
-            .add_systems(Startup, layout::load_marker_images)
+            .init_resource::<terrain::CurrentArena>()
+            .init_resource::<terrain::ArenaPending>()
+            .init_resource::<enemy::ArmyPending>()
+            .init_resource::<enemy::DraftedSquads>()
+            .init_asset::<terrain::ArenaCatalog>()
+            .register_asset_loader(RonLoader::<terrain::ArenaCatalog>::new(&["arenas.ron"]))
+            .init_asset::<enemy::ArmyCatalog>()
+            .register_asset_loader(RonLoader::<enemy::ArmyCatalog>::new(&["armies.ron"]))
+            .add_event::<victory::BattleOver>()
+            .add_plugins((units::UnitsPlugin, replay::ReplayPlugin))
+            .add_systems(
+                Startup,
+                (
+                    layout::load_marker_images,
+                    terrain::load_arenas,
+                    enemy::load_armies,
+                ),
+            )
             .add_systems(
                 OnEnter(GameState::InitBattle),
                 (
                     despawn_slots,
                     init_unlocked_slots,
+                    init_army,
                     layout::init_slots,
                     layout::init_units,
                 ),
@@ -35,22 +57,46 @@ impl Plugin for BattlePlugin {
                 Update,
                 (
                     finish_init_battle.run_if(in_state(GameState::InitBattle)),
-                    finish_pre_battle.run_if(in_state(GameState::PreBattle)),
+                    (
+                        orders::assign_orders,
+                        orders::update_order_labels,
+                        orders::handle_squad_clicks,
+                    )
+                        .chain()
+                        .run_if(in_state(GameState::PreBattle)),
                     layout::add_markers,
+                    terrain::spawn_terrain_sprites,
                     layout::spawn_marker_sprites,
                     (
-                        (
-                            camera::calc_bounds,
-                            camera::set_camera_velocity,
-                            camera::apply_camera_velocity,
-                        )
-                            .chain(),
-                        victory::detect_victory,
+                        camera::calc_bounds,
+                        camera::set_camera_velocity,
+                        camera::apply_camera_velocity,
                     )
+                        .chain()
+                        .run_if(in_state(GameState::Battle).or_else(in_state(GameState::Replay))),
+                    (victory::detect_victory, victory::finish_battle)
+                        .chain()
                         .run_if(in_state(GameState::Battle)),
                 ),
             )
-            .add_systems(OnExit(GameState::Victory), victory::increase_floor)
+            .add_systems(
+                OnEnter(GameState::PreBattle),
+                (
+                    orders::spawn_menu,
+                    boss::spawn_boss,
+                    terrain::spawn_terrain,
+                    enemy::generate_army,
+                ),
+            )
+            .add_systems(OnExit(GameState::PreBattle), orders::cleanup_menu)
+            .add_systems(
+                OnExit(GameState::Battle),
+                (boss::despawn_boss, terrain::despawn_terrain),
+            )
+            .add_systems(
+                OnExit(GameState::Victory),
+                (victory::increase_floor, request_army, request_arena),
+            )
             .add_systems(OnEnter(GameState::Defeat), defeat::spawn_menu)
             .add_systems(OnExit(GameState::Defeat), defeat::cleanup_menu);
     }
@@ -63,11 +109,6 @@ fn finish_init_battle(mut next_state: ResMut<NextState<GameState>>) {
     next_state.set(GameState::PreBattle);
 }
 
-fn finish_pre_battle(mut next_state: ResMut<NextState<GameState>>) {
-    info!("Exiting PreBattle");
-    next_state.set(GameState::Battle);
-}
-
 fn despawn_slots(mut commands: Commands, slots: Query<Entity, With<layout::SquadSlot>>) {
     for ent in &mut slots.iter() {
         commands.entity(ent).despawn_recursive();
@@ -78,3 +119,17 @@ fn init_unlocked_slots(mut commands: Commands) {
     commands.insert_resource(FriendlyUnlockedSlots::default());
     commands.insert_resource(EnemyUnlockedSlots::default());
 }
+
+fn init_army(mut commands: Commands) {
+    commands.insert_resource(enemy::DraftedSquads::default());
+    commands.insert_resource(enemy::ArmyPending(true));
+    commands.insert_resource(terrain::ArenaPending(true));
+}
+
+fn request_army(mut pending: ResMut<enemy::ArmyPending>) {
+    pending.0 = true;
+}
+
+fn request_arena(mut pending: ResMut<terrain::ArenaPending>) {
+    pending.0 = true;
+}
@@ -0,0 +1,200 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    menu::{
+        button::{ButtonAction, ButtonStyle},
+        colors,
+        sounds::SelectSound,
+        spawn_button,
+    },
+    rng::{RngStream, RunRng},
+};
+
+use super::units::{orders::SquadOrders, squad::Squad, Team};
+
+/// How close to a squad's flag a click has to be to select it, in world units.
+const CLICK_RADIUS: f32 = 16.0;
+
+#[derive(Component)]
+pub struct OrdersMenu;
+
+/// Text under a player squad's flag showing its orders.
+#[derive(Component)]
+pub struct OrderLabel(Entity);
+
+/// Gives squads without orders their default ones, and random ones for the enemy.
+pub fn assign_orders(
+    mut commands: Commands,
+    squads: Query<(Entity, &Team), (With<Squad>, Without<SquadOrders>)>,
+    mut rng: ResMut<RunRng>,
+) {
+    for (ent, team) in squads.iter() {
+        let orders = match team {
+            Team::Player => SquadOrders::default(),
+            Team::Enemy => SquadOrders::random(rng.stream(RngStream::SquadOrders)),
+        };
+
+        commands.entity(ent).insert(orders);
+    }
+}
+
+pub fn spawn_menu(
+    mut commands: Commands,
+    button_style: Res<ButtonStyle>,
+    asset_server: Res<AssetServer>,
+) {
+    let font = asset_server.load("font/vt323.ttf");
+
+    commands
+        .spawn((
+            OrdersMenu,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::FlexEnd,
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::bottom(Val::Px(20.0)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|p| {
+            p.spawn(
+                TextBundle::from_section(
+                    "Click a squad to change its orders, right click to set when it retreats.",
+                    TextStyle {
+                        color: Color::hex(colors::BG_LIGHT).unwrap(),
+                        font_size: 24.0,
+                        font: font.clone(),
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(10.0)),
+                    ..default()
+                }),
+            );
+
+            spawn_button(
+                p,
+                &button_style,
+                "Fight!",
+                font.clone(),
+                ButtonAction::Fight,
+            );
+        });
+}
+
+pub fn update_order_labels(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    squads: Query<
+        (Entity, &Team, &SquadOrders, Option<&OrderLabel>),
+        (With<Squad>, Or<(Changed<SquadOrders>, Without<OrderLabel>)>),
+    >,
+    mut texts: Query<&mut Text>,
+) {
+    for (ent, team, orders, label) in squads.iter() {
+        if *team != Team::Player {
+            continue;
+        }
+
+        if let Some(OrderLabel(label)) = label {
+            if let Ok(mut text) = texts.get_mut(*label) {
+                text.sections[0].value = orders.to_string();
+                continue;
+            }
+        }
+
+        let label = commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    orders.to_string(),
+                    TextStyle {
+                        color: Color::hex(colors::ACCENT).unwrap(),
+                        font_size: 16.0,
+                        font: asset_server.load("font/vt323.ttf"),
+                    },
+                ),
+                // Rendered at twice the size for crisper text
+                transform: Transform::from_xyz(0.0, -12.0, 1.0).with_scale(Vec3::splat(0.5)),
+                ..default()
+            })
+            .set_parent(ent)
+            .id();
+
+        commands.entity(ent).insert(OrderLabel(label));
+    }
+}
+
+/// Left click cycles through orders, right click through retreat thresholds.
+pub fn handle_squad_clicks(
+    buttons: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut squads: Query<(&Team, &GlobalTransform, &mut SquadOrders), With<Squad>>,
+    interactions: Query<&Interaction>,
+    mut select_writer: EventWriter<SelectSound>,
+) {
+    let left = buttons.just_pressed(MouseButton::Left);
+    let right = buttons.just_pressed(MouseButton::Right);
+
+    if !left && !right {
+        return;
+    }
+
+    // Clicks on the menu are meant for it, not the squads behind it
+    if interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let cursor = match windows.get_single().ok().and_then(|w| w.cursor_position()) {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    let cursor = match cameras
+        .iter()
+        .find_map(|(camera, transform)| camera.viewport_to_world_2d(transform, cursor))
+    {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    for (team, transform, mut orders) in squads.iter_mut() {
+        if *team != Team::Player
+            || transform.translation().truncate().distance(cursor) > CLICK_RADIUS
+        {
+            continue;
+        }
+
+        if left {
+            orders.order = orders.order.next();
+        } else {
+            orders.retreat_below = orders.next_retreat();
+        }
+
+        info!("Squad orders changed to: {}", *orders);
+        select_writer.send_default();
+    }
+}
+
+pub fn cleanup_menu(
+    mut commands: Commands,
+    menu: Query<Entity, With<OrdersMenu>>,
+    labels: Query<(Entity, &OrderLabel)>,
+) {
+    for ent in menu.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+
+    for (ent, OrderLabel(label)) in labels.iter() {
+        commands.entity(*label).despawn_recursive();
+        commands.entity(ent).remove::<OrderLabel>();
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_xpbd_2d::components::LinearVelocity;
+use serde::{Deserialize, Serialize};
+
+use crate::{rng::RunRng, Floor, GameState};
+
+use super::{
+    terrain::{self, spawn_layout, ArenaLayouts, CurrentArena},
+    units::{
+        ai::{AttackEvent, Dead, DeathEvent},
+        formation::Formation,
+        squad::{Squad, SquadCount, Unit, UnitType},
+        Team,
+    },
+    victory::BattleOver,
+};
+
+/// Set this environment variable to a replay file to watch it on startup.
+const REPLAY_ENV: &str = "TOWER_QUEST_REPLAY";
+const REPLAY_DIR: &str = "replays";
+
+/// Seconds between recorded unit positions.
+/// Playback interpolates in between.
+const SAMPLE_INTERVAL: f32 = 0.1;
+/// Positions are stored as fixed point with this many steps per world unit.
+const POSITION_SCALE: f32 = 10.0;
+/// Seconds to keep showing the final state once playback is done.
+const END_DELAY: f32 = 1.0;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorder>()
+            .add_systems(Startup, load_replay_from_env)
+            .add_systems(OnEnter(GameState::Battle), start_recording)
+            .add_systems(
+                Update,
+                (
+                    (record_battle, save_replay)
+                        .chain()
+                        .run_if(in_state(GameState::Battle)),
+                    play_replay.run_if(in_state(GameState::Replay)),
+                ),
+            )
+            .add_systems(
+                OnEnter(GameState::Replay),
+                (despawn_units, start_playback).chain(),
+            )
+            .add_systems(
+                OnExit(GameState::Replay),
+                (despawn_units, terrain::despawn_terrain),
+            );
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub floor: usize,
+    /// Name of the arena layout, if there was one
+    pub terrain: Option<String>,
+    pub squads: Vec<ReplaySquad>,
+    pub units: Vec<ReplayUnit>,
+    pub ticks: Vec<ReplayTick>,
+    pub winner: Option<Team>,
+}
+
+/// A squad as it was placed on its [`super::layout::SquadSlot`] before the battle.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplaySquad {
+    pub team: Team,
+    pub slot: [f32; 2],
+    pub unit: UnitType,
+    pub count: usize,
+    pub formation: Formation,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayUnit {
+    pub team: Team,
+    pub unit: UnitType,
+    /// First tick the unit appears in.
+    pub tick: u32,
+}
+
+/// Units are referred to by their index in [`Replay::units`].
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ReplayTick {
+    pub attacks: Vec<(u32, u32)>,
+    pub deaths: Vec<u32>,
+    /// Positions of every living unit, in the order they were added.
+    pub positions: Vec<[i16; 2]>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, bincode::Error> {
+        let file = File::open(path)?;
+        bincode::deserialize_from(BufReader::new(file))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), bincode::Error> {
+        let file = File::create(path)?;
+        bincode::serialize_into(BufWriter::new(file), self)
+    }
+}
+
+fn to_fixed(pos: Vec2) -> [i16; 2] {
+    [
+        (pos.x * POSITION_SCALE).round() as i16,
+        (pos.y * POSITION_SCALE).round() as i16,
+    ]
+}
+
+fn from_fixed(pos: [i16; 2]) -> Vec2 {
+    Vec2::new(pos[0] as f32, pos[1] as f32) / POSITION_SCALE
+}
+
+/// The replay that "Watch Replay" plays, and where to go afterwards.
+#[derive(Resource)]
+pub struct ReplayToPlay {
+    pub replay: Replay,
+    pub return_to: GameState,
+}
+
+#[derive(Resource, Default)]
+struct ReplayRecorder {
+    replay: Replay,
+    ids: HashMap<Entity, u32>,
+    alive: Vec<Entity>,
+    pending: ReplayTick,
+    elapsed: f32,
+}
+
+fn start_recording(
+    mut commands: Commands,
+    floor: Res<Floor>,
+    rng: Res<RunRng>,
+    arena: Res<CurrentArena>,
+    squads: Query<(&Transform, &Team, &UnitType, &SquadCount, &Formation), With<Squad>>,
+) {
+    let squads = squads
+        .iter()
+        .map(|(transform, team, unit, count, formation)| ReplaySquad {
+            team: team.clone(),
+            slot: transform.translation.truncate().to_array(),
+            unit: unit.clone(),
+            count: count.0,
+            formation: formation.clone(),
+        })
+        .collect();
+
+    commands.insert_resource(ReplayRecorder {
+        replay: Replay {
+            seed: rng.seed(),
+            floor: floor.0,
+            terrain: arena.0.clone(),
+            squads,
+            ..default()
+        },
+        ..default()
+    });
+}
+
+fn record_battle(
+    time: Res<Time>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut attacks: EventReader<AttackEvent>,
+    mut deaths: EventReader<DeathEvent>,
+    new_units: Query<(Entity, &Team, &UnitType), Added<Unit>>,
+    transforms: Query<&GlobalTransform>,
+) {
+    let recorder = recorder.as_mut();
+
+    for (ent, team, unit) in new_units.iter() {
+        let id = recorder.replay.units.len() as u32;
+
+        recorder.replay.units.push(ReplayUnit {
+            team: team.clone(),
+            unit: unit.clone(),
+            tick: recorder.replay.ticks.len() as u32,
+        });
+        recorder.ids.insert(ent, id);
+        recorder.alive.push(ent);
+    }
+
+    for event in attacks.read() {
+        let ids = (
+            recorder.ids.get(&event.attacker),
+            recorder.ids.get(&event.target),
+        );
+
+        if let (Some(attacker), Some(target)) = ids {
+            recorder.pending.attacks.push((*attacker, *target));
+        }
+    }
+
+    for event in deaths.read() {
+        if let Some(id) = recorder.ids.get(&event.unit) {
+            recorder.pending.deaths.push(*id);
+            recorder.alive.retain(|ent| *ent != event.unit);
+        }
+    }
+
+    recorder.elapsed += time.delta_seconds();
+
+    if recorder.elapsed < recorder.replay.ticks.len() as f32 * SAMPLE_INTERVAL {
+        return;
+    }
+
+    let mut tick = std::mem::take(&mut recorder.pending);
+
+    tick.positions = recorder
+        .alive
+        .iter()
+        .map(|ent| match transforms.get(*ent) {
+            Ok(transform) => to_fixed(transform.translation().truncate()),
+            Err(_) => [0, 0],
+        })
+        .collect();
+
+    recorder.replay.ticks.push(tick);
+}
+
+fn save_replay(
+    mut commands: Commands,
+    mut battle_over: EventReader<BattleOver>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    for event in battle_over.read() {
+        let mut replay = std::mem::take(&mut recorder.replay);
+        replay.winner = event.winner.clone();
+
+        let path =
+            PathBuf::from(REPLAY_DIR).join(format!("{}_floor{}.replay", replay.seed, replay.floor));
+
+        match std::fs::create_dir_all(REPLAY_DIR)
+            .map_err(bincode::Error::from)
+            .and_then(|_| replay.save(&path))
+        {
+            Ok(_) => info!("Saved replay to {}", path.display()),
+            Err(e) => error!("Failed to save replay: {}", e),
+        }
+
+        commands.insert_resource(ReplayToPlay {
+            replay,
+            return_to: GameState::Defeat,
+        });
+    }
+}
+
+fn load_replay_from_env(mut commands: Commands, mut next_state: ResMut<NextState<GameState>>) {
+    let path = match std::env::var(REPLAY_ENV) {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+
+    match Replay::load(&path) {
+        Ok(replay) => {
+            info!("Playing replay {}", path);
+
+            commands.insert_resource(ReplayToPlay {
+                replay,
+                return_to: GameState::Menu,
+            });
+            next_state.set(GameState::Replay);
+        }
+        Err(e) => error!("Failed to load replay {}: {}", path, e),
+    }
+}
+
+#[derive(Resource)]
+struct ReplayPlayback {
+    replay: Replay,
+    return_to: GameState,
+    tick: usize,
+    elapsed: f32,
+    /// Entity for each unit spawned so far, by unit index.
+    entities: Vec<Entity>,
+    /// Living units with the positions they are moving between.
+    alive: Vec<(Entity, Vec2, Vec2)>,
+}
+
+fn start_playback(
+    mut commands: Commands,
+    to_play: Option<Res<ReplayToPlay>>,
+    layouts: ArenaLayouts,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let to_play = match to_play {
+        Some(to_play) => to_play,
+        None => {
+            error!("No replay to play");
+            next_state.set(GameState::Menu);
+            return;
+        }
+    };
+
+    if let Some(name) = &to_play.replay.terrain {
+        match layouts.get(name) {
+            Some(layout) => spawn_layout(&mut commands, layout),
+            None => error!("Unknown arena layout: {}", name),
+        }
+    }
+
+    commands.insert_resource(ReplayPlayback {
+        replay: to_play.replay.clone(),
+        return_to: to_play.return_to,
+        tick: 0,
+        elapsed: 0.0,
+        entities: Vec::new(),
+        alive: Vec::new(),
+    });
+}
+
+fn play_replay(
+    mut commands: Commands,
+    time: Res<Time>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    mut attack_events: EventWriter<AttackEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut units: Query<(&mut Transform, &mut LinearVelocity), With<Unit>>,
+) {
+    let mut playback = match playback {
+        Some(playback) => playback,
+        None => return,
+    };
+    let playback = playback.as_mut();
+
+    playback.elapsed += time.delta_seconds();
+
+    let num_ticks = playback.replay.ticks.len();
+
+    if playback.tick >= num_ticks
+        && playback.elapsed > num_ticks as f32 * SAMPLE_INTERVAL + END_DELAY
+    {
+        next_state.set(playback.return_to);
+        return;
+    }
+
+    while playback.tick < num_ticks && playback.tick as f32 * SAMPLE_INTERVAL <= playback.elapsed {
+        let tick = &playback.replay.ticks[playback.tick];
+
+        // Spawn units that first appear in this tick
+        while let Some(unit) = playback.replay.units.get(playback.entities.len()) {
+            if unit.tick as usize > playback.tick {
+                break;
+            }
+
+            let ent = commands
+                .spawn((
+                    Unit,
+                    unit.team.clone(),
+                    unit.unit.clone(),
+                    LinearVelocity::default(),
+                    TransformBundle::default(),
+                    VisibilityBundle::default(),
+                ))
+                .id();
+
+            playback.entities.push(ent);
+            playback.alive.push((ent, Vec2::NAN, Vec2::NAN));
+        }
+
+        for (attacker, target) in tick.attacks.iter() {
+            attack_events.send(AttackEvent {
+                attacker: playback.entities[*attacker as usize],
+                target: playback.entities[*target as usize],
+            });
+        }
+
+        for id in tick.deaths.iter() {
+            let ent = playback.entities[*id as usize];
+
+            commands.entity(ent).insert(Dead);
+            death_events.send(DeathEvent { unit: ent });
+            playback.alive.retain(|(e, _, _)| *e != ent);
+        }
+
+        for ((_, from, to), pos) in playback.alive.iter_mut().zip(tick.positions.iter()) {
+            let pos = from_fixed(*pos);
+
+            *from = if to.is_nan() { pos } else { *to };
+            *to = pos;
+        }
+
+        playback.tick += 1;
+    }
+
+    // Interpolate from the previous tick towards the latest one
+    let tick_time = playback.tick.saturating_sub(1) as f32 * SAMPLE_INTERVAL;
+    let t = ((playback.elapsed - tick_time) / SAMPLE_INTERVAL).clamp(0.0, 1.0);
+
+    for (ent, from, to) in playback.alive.iter() {
+        if let Ok((mut transform, mut velocity)) = units.get_mut(*ent) {
+            let pos = from.lerp(*to, t);
+
+            transform.translation.x = pos.x;
+            transform.translation.y = pos.y;
+            velocity.0 = (*to - *from) / SAMPLE_INTERVAL;
+        }
+    }
+}
+
+fn despawn_units(mut commands: Commands, units: Query<Entity, With<Unit>>) {
+    for ent in units.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
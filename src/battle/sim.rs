@@ -0,0 +1,93 @@
+use bevy::{
+    app::AppExit,
+    ecs::schedule::common_conditions::{any_with_component, not},
+    prelude::*,
+};
+use bevy_xpbd_2d::{plugins::PhysicsPlugins, resources::Gravity};
+
+use crate::rng::RunRng;
+
+use super::{
+    units::{
+        catalog,
+        squad::{self, Unit},
+        Team, UnitsSimPlugin,
+    },
+    victory::{self, BattleOver},
+};
+
+/// Runs a battle without a window, rendering, audio or UI.
+///
+/// Meant to be added to an app built on `MinimalPlugins`, which should not add physics itself.
+/// Spawn squads (a [`squad::SquadBundle`] with a [`Team`] and a transform) during `Startup`,
+/// their units are spawned once the unit catalog has loaded and fight until one team is left.
+/// Squads without [`SquadOrders`](super::units::orders::SquadOrders) charge right away.
+/// Terrain can be added with [`spawn_layout`](super::terrain::spawn_layout).
+/// Insert [`FriendlyFire`](super::units::sight::FriendlyFire) to let ranged units hurt their allies.
+/// Insert a [`RunRng`] beforehand to make the battle reproducible,
+/// and [`StatModifiers`](super::units::modifiers::StatModifiers) to apply upgrades.
+/// The outcome is then stored in [`BattleResult`] and the app exits.
+///
+/// `App::run` hands the app over to its runner, so use [`run_battle`] to get the outcome back.
+pub struct BattleSimPlugin;
+
+impl Plugin for BattleSimPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<TransformPlugin>() {
+            app.add_plugins(TransformPlugin);
+        }
+
+        if !app.is_plugin_added::<HierarchyPlugin>() {
+            app.add_plugins(HierarchyPlugin);
+        }
+
+        if !app.is_plugin_added::<AssetPlugin>() {
+            app.add_plugins(AssetPlugin::default());
+        }
+
+        app.add_plugins((PhysicsPlugins::default(), UnitsSimPlugin))
+            .insert_resource(Gravity(Vec2::ZERO))
+            .init_resource::<RunRng>()
+            .add_event::<BattleOver>()
+            .add_systems(
+                Update,
+                (
+                    squad::spawn_units.run_if(
+                        catalog::catalog_loaded.and_then(not(any_with_component::<Unit>())),
+                    ),
+                    (victory::detect_victory, record_result).chain(),
+                ),
+            );
+    }
+}
+
+/// Outcome of a simulated battle.
+/// `winner` is `None` if both teams were wiped out.
+#[derive(Resource, Debug)]
+pub struct BattleResult {
+    pub winner: Option<Team>,
+}
+
+/// Steps the app until the battle is decided and takes its [`BattleResult`].
+pub fn run_battle(app: &mut App) -> BattleResult {
+    loop {
+        app.update();
+
+        if let Some(result) = app.world.remove_resource::<BattleResult>() {
+            return result;
+        }
+    }
+}
+
+fn record_result(
+    mut commands: Commands,
+    mut battle_over: EventReader<BattleOver>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    for event in battle_over.read() {
+        commands.insert_resource(BattleResult {
+            winner: event.winner.clone(),
+        });
+        app_exit.send(AppExit);
+    }
+}
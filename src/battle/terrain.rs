@@ -0,0 +1,330 @@
+use bevy::{ecs::system::SystemParam, prelude::*, reflect::TypePath};
+use bevy_xpbd_2d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    data::Validate,
+    rng::{RngStream, RunRng},
+    Floor,
+};
+
+use super::{
+    layout::{ARENA_HEIGHT, ARENA_WIDTH},
+    units::orders::SquadOrder,
+};
+
+const CATALOG_PATH: &str = "data/base.arenas.ron";
+/// Room kept free of obstacles around the waypoints of squad orders, so squads can reach them.
+const WAYPOINT_CLEARANCE: f32 = 16.0;
+
+/// Every arena layout, in the order they were defined.
+#[derive(Asset, TypePath, Deserialize)]
+#[serde(transparent)]
+pub struct ArenaCatalog(pub Vec<ArenaLayout>);
+
+#[derive(Clone, Deserialize)]
+pub struct ArenaLayout {
+    pub name: String,
+    /// First floor the layout can show up on
+    #[serde(default)]
+    pub min_floor: usize,
+    pub features: Vec<TerrainFeature>,
+}
+
+#[derive(Component, Clone, Deserialize)]
+pub struct TerrainFeature {
+    pub kind: TerrainKind,
+    pub shape: TerrainShape,
+    /// Center of the feature, from the center of the arena
+    pub position: (f32, f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum TerrainKind {
+    Rock,
+    Wall,
+    Mud,
+    Water,
+}
+
+impl TerrainKind {
+    /// Multiplier for the speed of units moving through it,
+    /// or `None` for features that units can't pass.
+    pub fn speed_factor(&self) -> Option<f32> {
+        match self {
+            TerrainKind::Rock | TerrainKind::Wall => None,
+            TerrainKind::Mud => Some(0.5),
+            TerrainKind::Water => Some(0.65),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            TerrainKind::Rock => Color::rgb(0.45, 0.42, 0.4),
+            TerrainKind::Wall => Color::rgb(0.35, 0.3, 0.3),
+            TerrainKind::Mud => Color::rgba(0.45, 0.3, 0.2, 0.6),
+            TerrainKind::Water => Color::rgba(0.3, 0.5, 0.8, 0.5),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum TerrainShape {
+    /// Width and height
+    Rect(f32, f32),
+    /// Radius
+    Circle(f32),
+}
+
+impl TerrainShape {
+    /// Whether `offset` from the center of the shape is inside it.
+    pub fn contains(&self, offset: Vec2) -> bool {
+        match self {
+            TerrainShape::Rect(width, height) => {
+                offset.x.abs() <= width / 2.0 && offset.y.abs() <= height / 2.0
+            }
+            TerrainShape::Circle(radius) => offset.length() <= *radius,
+        }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        match self {
+            TerrainShape::Rect(width, height) => Vec2::new(*width, *height),
+            TerrainShape::Circle(radius) => Vec2::splat(radius * 2.0),
+        }
+    }
+
+    /// The shape grown by `margin` on every side.
+    pub fn inflated(&self, margin: f32) -> Self {
+        match self {
+            TerrainShape::Rect(width, height) => {
+                TerrainShape::Rect(width + margin * 2.0, height + margin * 2.0)
+            }
+            TerrainShape::Circle(radius) => TerrainShape::Circle(radius + margin),
+        }
+    }
+
+    fn collider(&self) -> Collider {
+        match self {
+            TerrainShape::Rect(width, height) => Collider::cuboid(*width, *height),
+            TerrainShape::Circle(radius) => Collider::ball(*radius),
+        }
+    }
+}
+
+/// Ground that slows units down while they are on it.
+#[derive(Component)]
+pub struct SlowZone {
+    pub shape: TerrainShape,
+    pub speed_factor: f32,
+}
+
+/// Speed multiplier for a unit at `pos`, from the slowest zone it's in.
+pub fn speed_factor_at(zones: &[(Vec2, &SlowZone)], pos: Vec2) -> f32 {
+    zones
+        .iter()
+        .filter(|(center, zone)| zone.shape.contains(pos - *center))
+        .map(|(_, zone)| zone.speed_factor)
+        .fold(1.0, f32::min)
+}
+
+impl Validate for ArenaCatalog {
+    fn validate(&self) -> Result<(), String> {
+        if !self.0.iter().any(|layout| layout.min_floor == 0) {
+            return Err("at least one layout must be available from floor 0".to_string());
+        }
+
+        for layout in self.0.iter() {
+            layout
+                .validate()
+                .map_err(|e| format!("layout \"{}\": {}", layout.name, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for ArenaLayout {
+    fn validate(&self) -> Result<(), String> {
+        for (i, feature) in self.features.iter().enumerate() {
+            let size = feature.shape.size();
+
+            if size.x <= 0.0 || size.y <= 0.0 {
+                return Err(format!(
+                    "feature {}: size must be greater than 0, got {}",
+                    i, size
+                ));
+            }
+
+            let (x, y) = feature.position;
+
+            if x.abs() > ARENA_WIDTH / 2.0 || y.abs() > ARENA_HEIGHT / 2.0 {
+                return Err(format!(
+                    "feature {}: position ({}, {}) is outside the arena",
+                    i, x, y
+                ));
+            }
+
+            if feature.kind.speed_factor().is_some() {
+                continue;
+            }
+
+            let shape = feature.shape.inflated(WAYPOINT_CLEARANCE);
+
+            for order in SquadOrder::ALL {
+                if let Some(waypoint) = order.waypoint() {
+                    if shape.contains(waypoint - Vec2::new(x, y)) {
+                        return Err(format!(
+                            "feature {}: blocks the waypoint of {} at {}",
+                            i,
+                            order.name(),
+                            waypoint
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+pub struct ArenaCatalogHandle(pub Handle<ArenaCatalog>);
+
+pub fn load_arenas(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ArenaCatalogHandle(asset_server.load(CATALOG_PATH)));
+}
+
+/// Looks up layouts in the loaded [`ArenaCatalog`].
+#[derive(SystemParam)]
+pub struct ArenaLayouts<'w> {
+    handle: Option<Res<'w, ArenaCatalogHandle>>,
+    catalogs: Res<'w, Assets<ArenaCatalog>>,
+}
+
+impl<'w> ArenaLayouts<'w> {
+    pub fn catalog(&self) -> Option<&ArenaCatalog> {
+        self.catalogs.get(&self.handle.as_ref()?.0)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ArenaLayout> {
+        self.catalog()?.0.iter().find(|layout| layout.name == name)
+    }
+}
+
+/// Name of the layout the current battle is fought on.
+#[derive(Resource, Clone, Default)]
+pub struct CurrentArena(pub Option<String>);
+
+/// Set when the next battle needs a new layout, instead of the one in [`CurrentArena`].
+#[derive(Resource, Default)]
+pub struct ArenaPending(pub bool);
+
+/// Spawns each feature of a layout, without any sprites.
+pub fn spawn_layout(commands: &mut Commands, layout: &ArenaLayout) {
+    for feature in layout.features.iter() {
+        let (x, y) = feature.position;
+
+        // Below units, with obstacles drawn over the ground
+        let z = match feature.kind.speed_factor() {
+            Some(_) => -2.0,
+            None => -1.0,
+        };
+
+        let mut ent = commands.spawn((
+            feature.clone(),
+            TransformBundle::from_transform(Transform::from_xyz(x, y, z)),
+            VisibilityBundle::default(),
+        ));
+
+        match feature.kind.speed_factor() {
+            Some(speed_factor) => {
+                ent.insert(SlowZone {
+                    shape: feature.shape,
+                    speed_factor,
+                });
+            }
+            None => {
+                ent.insert((RigidBody::Static, feature.shape.collider()));
+            }
+        }
+    }
+}
+
+/// Picks a layout for the floor and lays it out.
+pub fn spawn_terrain(
+    mut commands: Commands,
+    floor: Res<Floor>,
+    layouts: ArenaLayouts,
+    mut current: ResMut<CurrentArena>,
+    mut pending: ResMut<ArenaPending>,
+    mut rng: ResMut<RunRng>,
+) {
+    // A continued run goes back to the layout it was saved on
+    if !pending.0 {
+        if let Some(layout) = current.0.as_ref().and_then(|name| layouts.get(name)) {
+            info!("Fighting on {}", layout.name);
+            spawn_layout(&mut commands, layout);
+        }
+
+        return;
+    }
+
+    pending.0 = false;
+
+    let catalog = match layouts.catalog() {
+        Some(catalog) => catalog,
+        None => {
+            error!("Arena catalog not loaded");
+            return;
+        }
+    };
+
+    let available = catalog
+        .0
+        .iter()
+        .filter(|layout| layout.min_floor <= floor.0)
+        .collect::<Vec<_>>();
+
+    if available.is_empty() {
+        current.0 = None;
+        return;
+    }
+
+    let layout = available[rng.stream(RngStream::Terrain).gen_range(0..available.len())];
+
+    info!("Fighting on {}", layout.name);
+
+    current.0 = Some(layout.name.clone());
+    spawn_layout(&mut commands, layout);
+}
+
+pub fn despawn_terrain(mut commands: Commands, features: Query<Entity, With<TerrainFeature>>) {
+    for ent in features.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+pub fn spawn_terrain_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    features: Query<(Entity, &TerrainFeature), Added<TerrainFeature>>,
+) {
+    for (ent, feature) in features.iter() {
+        let sprite = Sprite {
+            color: feature.kind.color(),
+            custom_size: Some(feature.shape.size()),
+            ..default()
+        };
+
+        // Rects are drawn with the default white texture
+        let image = match feature.shape {
+            TerrainShape::Rect(..) => Handle::<Image>::default(),
+            TerrainShape::Circle(_) => asset_server.load("images/arena/Circle.png"),
+        };
+
+        commands.entity(ent).insert((sprite, image));
+    }
+}
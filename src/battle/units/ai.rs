@@ -1,24 +1,67 @@
-use bevy::prelude::*;
-use bevy_xpbd_2d::components::{Collider, LinearVelocity, RigidBody};
-
-use crate::rewards::effects::{EnemySpeedModifier, FriendlySpeedModifier};
-
-use super::{animation::AttackEvent, squad::UnitType, Team};
+use bevy::{prelude::*, utils::HashMap};
+use bevy_xpbd_2d::components::{ColliderDensity, LinearVelocity};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    battle::terrain::{speed_factor_at, SlowZone},
+    rng::{RngStream, RunRng},
+};
+
+use super::{
+    damage::{deal_damage, Damage, DamageEvent, DamageRoll, DamageTarget, DamageType, Penetration},
+    knockback::{Knockback, Stagger},
+    morale::team_edge,
+    orders::{FormationOffset, SquadBehavior, SquadState, ARRIVE_RADIUS},
+    pathfinding::{FlowFields, NavGrid},
+    projectile::{ProjectileBundle, RangedAttack},
+    sight::BlockedShot,
+    spatial::SpatialGrid,
+    squad::UnitType,
+    status::{OnHit, StatusEffects},
+    steering,
+    targeting::{TargetStats, TargetingStrategy, SEARCH_RADIUS},
+    Team,
+};
 
 #[derive(Component, Clone, Default)]
 pub struct MovementSpeed(pub f32);
 
-#[derive(Component, Clone, Default)]
+#[derive(Component, Clone, Default, Deserialize)]
 pub enum MovementStyle {
     #[default]
     Direct,
     WithinRange,
 }
 
-#[derive(Component)]
+/// What a unit does to its target.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum UnitRole {
+    #[default]
+    Fighter,
+    /// Heals wounded allies instead of attacking, by its attack damage
+    Healer,
+}
+
+/// How far healers look for wounded allies.
+const HEAL_SEARCH_RADIUS: f32 = 100.0;
+/// Seconds a unit sticks with its target before searching for a better one,
+/// unless the target dies or gets away first. Nearest targets are updated every frame.
+const RETARGET_INTERVAL: f32 = 0.5;
+
+#[derive(Component, PartialEq)]
 pub enum Movement {
-    Direct { target: Vec3 },
-    WithinRange { target: Vec3, range: f32 },
+    Direct {
+        target: Vec3,
+    },
+    WithinRange {
+        target: Vec3,
+        range: f32,
+    },
+    /// Stops once it reaches the target
+    Arrive {
+        target: Vec3,
+    },
 }
 
 #[derive(Component, Clone, Default)]
@@ -27,7 +70,7 @@ pub struct AttackDamage(pub f32);
 #[derive(Component, Clone, Default)]
 pub struct AttackRange(pub f32);
 
-#[derive(Component)]
+#[derive(Component, PartialEq)]
 pub struct AttackTarget(pub Entity);
 
 #[derive(Component, Clone, Default)]
@@ -36,130 +79,440 @@ pub struct AttackCooldown(pub f32);
 #[derive(Component)]
 pub struct LastAttackTime(pub f32);
 
+/// Seconds until the unit searches for a new target.
+#[derive(Component, Clone, Default)]
+pub struct Retarget(pub f32);
+
 #[derive(Component, Clone, Default)]
 pub struct Health(pub f32);
 
+/// Health a unit spawned with, which healing can't go above.
+#[derive(Component, Clone, Default)]
+pub struct MaxHealth(pub f32);
+
 #[derive(Component)]
 pub struct Dead;
 
+#[derive(Event)]
+pub struct AttackEvent {
+    pub attacker: Entity,
+    pub target: Entity,
+}
+
+#[derive(Event)]
+pub struct DeathEvent {
+    pub unit: Entity,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn set_target(
     mut commands: Commands,
-    units: Query<
+    time: Res<Time>,
+    grid: Res<SpatialGrid>,
+    mut rng: ResMut<RunRng>,
+    mut units: Query<
         (
             Entity,
             &MovementStyle,
+            &UnitRole,
+            &TargetingStrategy,
             &Team,
             &GlobalTransform,
             &AttackRange,
+            &mut Retarget,
+            Option<&mut AttackTarget>,
+            Option<&mut Movement>,
+            Option<(&Parent, &FormationOffset)>,
         ),
         Without<Dead>,
     >,
+    healths: Query<(&Health, &MaxHealth)>,
+    stats: TargetStats,
+    squads: Query<&SquadState>,
+    transforms: Query<&GlobalTransform>,
 ) {
-    for (ent, style, team, transform, range) in units.iter() {
-        let nearest_enemy = units
-            .iter()
-            .filter(|(_, _, t, _, _)| **t != *team)
-            .map(|(e, _, _, t, _)| (e, t, transform.translation().distance(t.translation())))
-            .min_by(|(_, _, d1), (_, _, d2)| d1.partial_cmp(d2).unwrap());
-
-        let (target_ent, target_translation) = match nearest_enemy {
-            Some((e, t, _)) => (e, t.translation()),
-            None => {
+    let rng = rng.stream(RngStream::Targeting);
+    let delta = time.delta_seconds();
+
+    for (
+        ent,
+        style,
+        role,
+        strategy,
+        team,
+        transform,
+        range,
+        mut retarget,
+        attack_target,
+        movement,
+        squad,
+    ) in units.iter_mut()
+    {
+        let translation = transform.translation();
+        let pos = translation.truncate();
+        let current = attack_target.as_ref().map(|target| target.0);
+
+        let (behavior, offset) = match squad {
+            Some((parent, offset)) => match squads.get(parent.get()) {
+                Ok(state) => (state.behavior, offset.0),
+                Err(_) => (SquadBehavior::Engage, offset.0),
+            },
+            None => (SquadBehavior::Engage, Vec2::ZERO),
+        };
+
+        let destination = match behavior {
+            SquadBehavior::Engage => None,
+            // Keep formation while the squad is following its orders
+            SquadBehavior::MoveTo(anchor) => Some(anchor + offset),
+            SquadBehavior::Rout => Some(Vec2::new(team_edge(team), pos.y)),
+        };
+
+        if let Some(destination) = destination {
+            if attack_target.is_some() {
                 commands.entity(ent).remove::<AttackTarget>();
-                continue;
             }
+
+            let new_movement = Movement::Arrive {
+                target: destination.extend(translation.z),
+            };
+
+            match movement {
+                Some(mut movement) => {
+                    movement.set_if_neq(new_movement);
+                }
+                None => {
+                    commands.entity(ent).insert(new_movement);
+                }
+            }
+
+            continue;
+        }
+
+        retarget.0 -= delta;
+
+        let search_radius = match role {
+            UnitRole::Fighter => SEARCH_RADIUS,
+            UnitRole::Healer => HEAL_SEARCH_RADIUS,
         };
 
-        let mut entity = commands.entity(ent);
-        entity.insert(AttackTarget(target_ent));
+        // Searching for anything but the nearest enemy is costly, so units only do it now and then
+        let kept = match (role, strategy) {
+            (UnitRole::Fighter, TargetingStrategy::Nearest) => None,
+            _ if retarget.0 > 0.0 => current.and_then(|current| {
+                let (health, _) = healths.get(current).ok()?;
+                let target_pos = transforms.get(current).ok()?.translation().truncate();
+
+                (health.0 > 0.0 && pos.distance(target_pos) <= search_radius)
+                    .then_some((current, target_pos))
+            }),
+            _ => None,
+        };
 
-        match style {
-            MovementStyle::Direct => {
-                entity.insert(Movement::Direct {
-                    target: target_translation,
-                });
+        let target = match kept {
+            Some(kept) => Some(kept),
+            None => {
+                retarget.0 = RETARGET_INTERVAL;
+
+                match role {
+                    UnitRole::Fighter => strategy.choose(&grid, &stats, team, pos, current, rng),
+                    // Stick with the nearest ally when nobody needs healing
+                    UnitRole::Healer => {
+                        most_wounded_ally(&grid, &healths, ent, team, pos).or_else(|| {
+                            grid.nearest_ally(team, pos, ent)
+                                .map(|(e, pos, _)| (e, pos))
+                        })
+                    }
+                }
             }
-            MovementStyle::WithinRange => {
-                entity.insert(Movement::WithinRange {
-                    target: target_translation,
-                    range: range.0,
-                });
+        };
+
+        let (target_ent, target_translation) = match target {
+            Some((e, pos)) => (e, pos.extend(translation.z)),
+            None => {
+                if attack_target.is_some() {
+                    commands.entity(ent).remove::<AttackTarget>();
+                }
+                continue;
             }
         };
+
+        let new_movement = match style {
+            MovementStyle::Direct => Movement::Direct {
+                target: target_translation,
+            },
+            MovementStyle::WithinRange => Movement::WithinRange {
+                target: target_translation,
+                range: range.0,
+            },
+        };
+
+        // Only touch components that actually changed, inserting is much slower
+        match attack_target {
+            Some(mut attack_target) => {
+                attack_target.set_if_neq(AttackTarget(target_ent));
+            }
+            None => {
+                commands.entity(ent).insert(AttackTarget(target_ent));
+            }
+        }
+
+        match movement {
+            Some(mut movement) => {
+                movement.set_if_neq(new_movement);
+            }
+            None => {
+                commands.entity(ent).insert(new_movement);
+            }
+        }
     }
 }
 
+fn most_wounded_ally(
+    grid: &SpatialGrid,
+    healths: &Query<(&Health, &MaxHealth)>,
+    healer: Entity,
+    team: &Team,
+    pos: Vec2,
+) -> Option<(Entity, Vec2)> {
+    grid.allies_within(team, pos, HEAL_SEARCH_RADIUS)
+        .filter(|(ent, _)| *ent != healer)
+        .filter_map(|(ent, pos)| {
+            let (health, max_health) = healths.get(ent).ok()?;
+            let fraction = health.0 / max_health.0;
+
+            if fraction < 1.0 {
+                Some((ent, pos, fraction))
+            } else {
+                None
+            }
+        })
+        .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        .map(|(ent, pos, _)| (ent, pos))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn move_units(
-    friendly_speed_modifier: Res<FriendlySpeedModifier>,
-    enemy_speed_modifier: Res<EnemySpeedModifier>,
+    time: Res<Time>,
+    grid: Res<SpatialGrid>,
+    nav_grid: Res<NavGrid>,
+    mut flow_fields: ResMut<FlowFields>,
     mut units: Query<
         (
-            &mut GlobalTransform,
+            Entity,
+            &GlobalTransform,
             &mut LinearVelocity,
+            &mut Stagger,
             &Team,
             &Movement,
             &MovementSpeed,
+            Option<&StatusEffects>,
+            Option<(&Parent, &FormationOffset)>,
+            Option<&BlockedShot>,
         ),
         Without<Dead>,
     >,
+    zones: Query<(&GlobalTransform, &SlowZone)>,
+    squads: Query<&SquadState>,
+    mut headings: Local<HashMap<Entity, (Vec2, usize)>>,
 ) {
-    for (transform, mut velocity, team, movement, speed) in units.iter_mut() {
-        let direction = match movement {
+    let delta = time.delta_seconds();
+
+    // Heading of each squad before any of its units move, for alignment
+    headings.clear();
+
+    for (_, _, velocity, .., squad, _) in units.iter() {
+        if let Some((parent, _)) = squad {
+            let (sum, count) = headings.entry(parent.get()).or_default();
+            *sum += velocity.0.normalize_or_zero();
+            *count += 1;
+        }
+    }
+
+    let zones = zones
+        .iter()
+        .map(|(transform, zone)| (transform.translation().truncate(), zone))
+        .collect::<Vec<_>>();
+
+    for (
+        ent,
+        transform,
+        mut velocity,
+        mut stagger,
+        team,
+        movement,
+        speed,
+        status,
+        squad,
+        blocked,
+    ) in units.iter_mut()
+    {
+        let status_factor = match status {
+            Some(status) => status.speed_factor(),
+            None => 1.0,
+        };
+
+        let pos = transform.translation().truncate();
+        let speed_factor = status_factor * speed_factor_at(&zones, pos);
+
+        if speed_factor == 0.0 {
+            stagger.apply(&mut velocity, Some(Vec2::ZERO), delta);
+            continue;
+        }
+
+        let mut towards = |target: Vec2| flow_fields.direction(&nav_grid, pos, target);
+
+        // Also how far the unit still has to go before it can attack
+        let (direction, target_distance) = match movement {
             Movement::Direct { target } => {
-                let direction = *target - transform.translation();
-                direction.normalize()
+                let target = target.truncate();
+                (Some(towards(target)), pos.distance(target))
             }
             Movement::WithinRange { target, range } => {
-                let translation = transform.translation();
-                let distance = translation.distance(*target);
+                let target = target.truncate();
+                let distance = pos.distance(target) - range;
+
+                if distance <= 0.0 {
+                    // In range but something is in the way, so step aside for a clear shot
+                    let direction = blocked.map(|blocked| reposition(ent, pos, target, blocked));
+                    (direction, 0.0)
+                } else {
+                    (Some(towards(target)), distance)
+                }
+            }
+            Movement::Arrive { target } => {
+                let target = target.truncate();
 
-                if distance <= *range {
+                if pos.distance(target) <= ARRIVE_RADIUS {
+                    stagger.apply(&mut velocity, Some(Vec2::ZERO), delta);
                     continue;
                 }
 
-                let direction = *target - translation;
-                direction.normalize()
+                // Already heading for its place in the formation
+                (Some(towards(target)), 0.0)
             }
         };
 
-        let speed_modifier = match team {
-            Team::Player => &friendly_speed_modifier.0,
-            Team::Enemy => &enemy_speed_modifier.0,
+        let direction = match direction {
+            Some(direction) => direction,
+            None => {
+                stagger.apply(&mut velocity, None, delta);
+                continue;
+            }
         };
 
-        let speed = speed.0 * speed_modifier.0;
-        let vel = direction * speed;
+        let slot = squad.and_then(|(parent, offset)| {
+            let state = squads.get(parent.get()).ok()?;
 
-        velocity.x = vel.x;
-        velocity.y = vel.y;
+            if state.behavior == SquadBehavior::Engage {
+                Some(state.anchor + offset.0)
+            } else {
+                None
+            }
+        });
+
+        let cohesion = match slot {
+            Some(slot) => steering::cohesion(pos, slot, target_distance),
+            None => Vec2::ZERO,
+        };
+
+        // Squads move as one, until they spread out to fight
+        let alignment = match squad {
+            Some((parent, _)) => {
+                let fade = match slot {
+                    Some(_) => steering::formation_fade(target_distance),
+                    None => 1.0,
+                };
+
+                steering::alignment(&headings, parent.get()) * fade
+            }
+            None => Vec2::ZERO,
+        };
+
+        let steered = steering::blend(
+            direction,
+            steering::separation(&grid, ent, team, pos),
+            cohesion,
+            alignment,
+            steering::avoidance(&nav_grid, pos, direction),
+        );
+
+        stagger.apply(&mut velocity, Some(steered * speed.0 * speed_factor), delta);
     }
 }
 
+/// Direction to step in to get a clear shot at `target`, away from whatever blocks it.
+fn reposition(ent: Entity, pos: Vec2, target: Vec2, blocked: &BlockedShot) -> Vec2 {
+    let aim = (target - pos).normalize_or_zero();
+    let side = aim.perp_dot(blocked.obstacle - pos);
+
+    // Units split up when the obstacle is dead ahead, instead of all going the same way
+    let side = match side == 0.0 {
+        true => match ent.index() % 2 {
+            0 => 1.0,
+            _ => -1.0,
+        },
+        false => side.signum(),
+    };
+
+    -aim.perp() * side
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn attack(
     mut commands: Commands,
     time: Res<Time>,
+    mut rng: ResMut<RunRng>,
     mut attack_events: EventWriter<AttackEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
     attackers: Query<
         (
             Entity,
-            &UnitType,
+            &UnitRole,
             &AttackRange,
             &AttackTarget,
             &AttackDamage,
             &AttackCooldown,
+            &DamageType,
+            &DamageRoll,
+            &Penetration,
             Option<&LastAttackTime>,
+            Option<&OnHit>,
+            Option<(&RangedAttack, &Team, &UnitType)>,
+            (&Knockback, &ColliderDensity),
         ),
         Without<Dead>,
     >,
-    mut healths: Query<&mut Health>,
+    mut targets: Query<DamageTarget>,
+    mut statuses: Query<&mut StatusEffects>,
+    max_healths: Query<&MaxHealth>,
     transforms: Query<&GlobalTransform>,
-    mut swing_writer: EventWriter<super::sounds::SwingSound>,
-    mut death_writer: EventWriter<super::sounds::DeathSound>,
+    blocked: Query<(), With<BlockedShot>>,
 ) {
     let now = time.elapsed_seconds();
 
-    for (ent, unit, range, target, damage, cooldown, last) in attackers.iter() {
+    for (
+        ent,
+        role,
+        range,
+        target,
+        damage,
+        cooldown,
+        damage_type,
+        roll,
+        penetration,
+        last,
+        on_hit,
+        ranged,
+        (knockback, density),
+    ) in attackers.iter()
+    {
+        if let Ok(status) = statuses.get(ent) {
+            if status.is_stunned() {
+                continue;
+            }
+        }
+
         let translation = transforms.get(ent).unwrap().translation();
         let target_translation = transforms.get(target.0).unwrap().translation();
         let distance = translation.distance(target_translation);
@@ -177,17 +530,45 @@ pub fn attack(
             continue;
         }
 
+        // Hold fire until the unit has a clear shot, instead of wasting it on what's in the way
+        if ranged.is_some() && blocked.contains(ent) {
+            continue;
+        }
+
+        if *role == UnitRole::Healer {
+            let (mut target_unit, max_health) =
+                match (targets.get_mut(target.0), max_healths.get(target.0)) {
+                    (Ok(target_unit), Ok(max_health)) => (target_unit, max_health),
+                    _ => continue,
+                };
+
+            // Don't waste the cooldown on allies that are dead or unhurt
+            if target_unit.health.0 <= 0.0 || target_unit.health.0 >= max_health.0 {
+                continue;
+            }
+
+            commands.entity(ent).insert(LastAttackTime(now));
+
+            attack_events.send(AttackEvent {
+                attacker: ent,
+                target: target.0,
+            });
+
+            target_unit.health.0 = (target_unit.health.0 + damage.0).min(max_health.0);
+            continue;
+        }
+
         commands.entity(ent).insert(LastAttackTime(now));
 
-        let mut health = match healths.get_mut(target.0) {
-            Ok(health) => health,
+        let mut target_unit = match targets.get_mut(target.0) {
+            Ok(target_unit) => target_unit,
             Err(_) => {
                 error!("Target has no health component!");
                 continue;
             }
         };
 
-        if health.0 <= 0.0 {
+        if target_unit.health.0 <= 0.0 {
             debug!("Target already dead!");
             continue;
         }
@@ -197,27 +578,65 @@ pub fn attack(
             target: target.0,
         });
 
-        health.0 -= damage.0;
+        let (amount, critical) = roll.roll(damage.0, rng.stream(RngStream::DamageRoll));
+
+        let damage = Damage {
+            amount,
+            damage_type: *damage_type,
+            penetration: penetration.0,
+            // Heavier units hit harder
+            knockback: knockback.0 * density.0,
+            critical,
+        };
 
-        if health.0 <= 0.0 {
-            death_writer.send_default();
+        if let Some((ranged, team, unit)) = ranged {
+            let aim = (target_translation - translation)
+                .truncate()
+                .normalize_or_zero();
+            let spread = if ranged.spread > 0.0 {
+                rng.stream(RngStream::ProjectileSpread)
+                    .gen_range(-ranged.spread..=ranged.spread)
+            } else {
+                0.0
+            };
+
+            let mut projectile = commands.spawn(ProjectileBundle::new(
+                ent,
+                team.clone(),
+                unit.clone(),
+                ranged,
+                damage,
+                range.0,
+                translation.truncate(),
+                Vec2::from_angle(spread).rotate(aim),
+            ));
+
+            if let Some(on_hit) = on_hit {
+                projectile.insert(on_hit.clone());
+            }
 
-            commands
-                .entity(target.0)
-                .insert((Dead, Visibility::Hidden))
-                .remove::<Collider>()
-                .remove::<Movement>()
-                .remove::<RigidBody>()
-                .remove::<TextureAtlasSprite>();
+            continue;
         }
 
-        match unit {
-            UnitType::Knight => {
-                swing_writer.send_default();
-            }
-            UnitType::Archer => {
-                swing_writer.send_default();
-            }
+        let amount = deal_damage(
+            &mut commands,
+            &mut death_events,
+            target.0,
+            &mut target_unit,
+            damage,
+            (target_translation - translation)
+                .truncate()
+                .normalize_or_zero(),
+        );
+
+        damage_events.send(DamageEvent {
+            target: target.0,
+            amount,
+            critical,
+        });
+
+        if let (Some(on_hit), Ok(mut status)) = (on_hit, statuses.get_mut(target.0)) {
+            on_hit.apply_to(&mut status);
         }
     }
 }
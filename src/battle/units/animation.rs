@@ -1,11 +1,7 @@
 use bevy::prelude::*;
 use bevy_xpbd_2d::components::LinearVelocity;
 
-#[derive(Event)]
-pub struct AttackEvent {
-    pub attacker: Entity,
-    pub target: Entity,
-}
+use super::{ai::AttackEvent, catalog::UnitDefinitions, squad::UnitType};
 
 #[derive(Component)]
 pub struct AnimationTimer(Timer);
@@ -16,11 +12,24 @@ pub struct AtlasAnimation {
     pub end: usize,
 }
 
-pub fn animate_attack(mut commands: Commands, mut events: EventReader<AttackEvent>) {
+pub fn animate_attack(
+    mut commands: Commands,
+    mut events: EventReader<AttackEvent>,
+    definitions: UnitDefinitions,
+    units: Query<&UnitType>,
+) {
     for event in events.read() {
+        let frames = match units.get(event.attacker).map(|unit| definitions.get(unit)) {
+            Ok(Some(definition)) => definition.sprite.frames,
+            _ => continue,
+        };
+
         commands.entity(event.attacker).insert((
             AnimationTimer(Timer::from_seconds(0.25, TimerMode::Once)),
-            AtlasAnimation { start: 0, end: 3 },
+            AtlasAnimation {
+                start: 0,
+                end: frames,
+            },
         ));
     }
 }
@@ -0,0 +1,341 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::data::Validate;
+
+use super::{
+    ai::{Dead, DeathEvent, MaxHealth},
+    damage::{deal_damage, Damage, DamageEvent, DamageTarget, DamageType},
+    spatial::SpatialGrid,
+    squad::{UnitSpawner, UnitType},
+    status::StatusEffects,
+    Team,
+};
+
+/// Distance from the boss that summoned units appear at.
+const SUMMON_RADIUS: f32 = 15.0;
+
+#[derive(Clone, Deserialize)]
+pub struct BossDefinition {
+    /// Phases in the order the boss goes through them, the first one is active from the start
+    pub phases: Vec<BossPhase>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct BossPhase {
+    /// Fraction of its health the boss has to drop below to enter this phase
+    pub health_below: f32,
+    pub abilities: Vec<BossAbility>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub enum BossAbility {
+    /// Damages and knocks back every enemy around the boss
+    Slam {
+        damage: f32,
+        radius: f32,
+        #[serde(default)]
+        damage_type: DamageType,
+        /// Impulse applied per point of damage dealt
+        #[serde(default)]
+        knockback: f32,
+        /// Seconds between slams
+        cooldown: f32,
+    },
+    /// Calls in units to fight alongside the boss
+    Summon {
+        unit: UnitType,
+        count: usize,
+        /// Seconds between summons
+        cooldown: f32,
+    },
+}
+
+impl BossAbility {
+    pub fn cooldown(&self) -> f32 {
+        match self {
+            BossAbility::Slam { cooldown, .. } | BossAbility::Summon { cooldown, .. } => *cooldown,
+        }
+    }
+}
+
+impl Validate for BossDefinition {
+    fn validate(&self) -> Result<(), String> {
+        if self.phases.is_empty() {
+            return Err("at least one phase is required".to_string());
+        }
+
+        let mut previous = f32::INFINITY;
+
+        for (i, phase) in self.phases.iter().enumerate() {
+            if phase.health_below <= 0.0 || phase.health_below > 1.0 {
+                return Err(format!(
+                    "phase {}: health_below must be greater than 0 and at most 1, got {}",
+                    i, phase.health_below
+                ));
+            }
+
+            if phase.health_below >= previous {
+                return Err(format!(
+                    "phase {}: health_below must be lower than the previous phase's",
+                    i
+                ));
+            }
+
+            previous = phase.health_below;
+
+            for ability in phase.abilities.iter() {
+                ability
+                    .validate()
+                    .map_err(|e| format!("phase {}: {:?}: {}", i, ability, e))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for BossAbility {
+    fn validate(&self) -> Result<(), String> {
+        if self.cooldown() <= 0.0 {
+            return Err(format!(
+                "cooldown must be greater than 0, got {}",
+                self.cooldown()
+            ));
+        }
+
+        match self {
+            BossAbility::Slam {
+                damage,
+                radius,
+                knockback,
+                ..
+            } => {
+                if *radius <= 0.0 {
+                    return Err(format!("radius must be greater than 0, got {}", radius));
+                }
+
+                if *damage < 0.0 || *knockback < 0.0 {
+                    return Err("damage and knockback must not be negative".to_string());
+                }
+            }
+            BossAbility::Summon { count, .. } => {
+                if *count == 0 {
+                    return Err("count must be at least 1".to_string());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A boss's progress through its phases.
+#[derive(Component)]
+pub struct Boss {
+    phases: Vec<BossPhase>,
+    phase: usize,
+    /// Seconds until each ability of the current phase can be used again
+    cooldowns: Vec<f32>,
+}
+
+impl Boss {
+    pub fn new(definition: &BossDefinition) -> Self {
+        // Abilities start on cooldown so the battle doesn't open with all of them at once
+        let cooldowns = match definition.phases.first() {
+            Some(phase) => phase.abilities.iter().map(|a| a.cooldown()).collect(),
+            None => Vec::new(),
+        };
+
+        Self {
+            phases: definition.phases.clone(),
+            phase: 0,
+            cooldowns,
+        }
+    }
+
+    /// Replaces the phases with reloaded ones, staying in the current phase
+    /// and keeping the cooldowns of its abilities.
+    pub fn reload(&mut self, definition: &BossDefinition) {
+        let mut boss = Self::new(definition);
+        boss.phase = self.phase.min(boss.phases.len().saturating_sub(1));
+
+        if let Some(phase) = boss.phases.get(boss.phase) {
+            boss.cooldowns = phase
+                .abilities
+                .iter()
+                .enumerate()
+                .map(|(i, ability)| {
+                    self.cooldowns
+                        .get(i)
+                        .copied()
+                        .unwrap_or_else(|| ability.cooldown())
+                })
+                .collect();
+        }
+
+        *self = boss;
+    }
+
+    /// Moves to the last phase whose threshold `health` is below.
+    /// Returns true if the phase changed.
+    fn update_phase(&mut self, health: f32) -> bool {
+        let phase = self
+            .phases
+            .iter()
+            .rposition(|phase| health < phase.health_below)
+            .unwrap_or(0)
+            .max(self.phase);
+
+        if phase == self.phase {
+            return false;
+        }
+
+        self.phase = phase;
+        // A new phase opens with all of its abilities ready
+        self.cooldowns = vec![0.0; self.phases[phase].abilities.len()];
+
+        true
+    }
+}
+
+/// Sent when a boss slams the ground.
+#[derive(Event)]
+pub struct SlamEvent {
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_bosses(
+    mut commands: Commands,
+    time: Res<Time>,
+    grid: Res<SpatialGrid>,
+    spawner: UnitSpawner,
+    mut death_events: EventWriter<DeathEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut slam_events: EventWriter<SlamEvent>,
+    mut bosses: Query<
+        (
+            Entity,
+            &mut Boss,
+            &Team,
+            &GlobalTransform,
+            &MaxHealth,
+            Option<&StatusEffects>,
+        ),
+        Without<Dead>,
+    >,
+    mut targets: Query<DamageTarget>,
+) {
+    let delta = time.delta_seconds();
+
+    for (ent, mut boss, team, transform, max_health, status) in bosses.iter_mut() {
+        let health = match targets.get(ent) {
+            Ok(target) => target.health.0 / max_health.0,
+            Err(_) => continue,
+        };
+
+        if boss.update_phase(health) {
+            info!("Boss {:?} entered phase {}", ent, boss.phase + 1);
+        }
+
+        for cooldown in boss.cooldowns.iter_mut() {
+            *cooldown -= delta;
+        }
+
+        if let Some(status) = status {
+            if status.is_stunned() {
+                continue;
+            }
+        }
+
+        let pos = transform.translation().truncate();
+        let boss = boss.as_mut();
+
+        for (ability, cooldown) in boss.phases[boss.phase]
+            .abilities
+            .iter()
+            .zip(boss.cooldowns.iter_mut())
+        {
+            if *cooldown > 0.0 {
+                continue;
+            }
+
+            match ability {
+                BossAbility::Slam {
+                    damage,
+                    radius,
+                    damage_type,
+                    knockback,
+                    ..
+                } => {
+                    let hits = grid.enemies_within(team, pos, *radius).collect::<Vec<_>>();
+
+                    // Save the slam for when it would hit something
+                    if hits.is_empty() {
+                        continue;
+                    }
+
+                    let damage = Damage {
+                        amount: *damage,
+                        damage_type: *damage_type,
+                        penetration: 0.0,
+                        knockback: *knockback,
+                        critical: false,
+                    };
+
+                    for (target, target_pos) in hits {
+                        let mut target_unit = match targets.get_mut(target) {
+                            Ok(target_unit) => target_unit,
+                            Err(_) => continue,
+                        };
+
+                        let amount = deal_damage(
+                            &mut commands,
+                            &mut death_events,
+                            target,
+                            &mut target_unit,
+                            damage,
+                            (target_pos - pos).normalize_or_zero(),
+                        );
+
+                        damage_events.send(DamageEvent {
+                            target,
+                            amount,
+                            critical: false,
+                        });
+                    }
+
+                    slam_events.send(SlamEvent {
+                        position: pos,
+                        radius: *radius,
+                    });
+                }
+                BossAbility::Summon { unit, count, .. } => {
+                    let definition = match spawner.definition(unit, team, None) {
+                        Some(definition) => definition,
+                        None => {
+                            error!("Unknown unit type: {:?}", unit);
+                            continue;
+                        }
+                    };
+
+                    info!("Boss {:?} summoned {} {:?}", ent, count, unit);
+
+                    // Summoned units have no squad, so they just fight
+                    for i in 0..*count {
+                        let angle = i as f32 / *count as f32 * TAU;
+                        let offset = Vec2::from_angle(angle) * SUMMON_RADIUS;
+
+                        spawner.spawn(&mut commands, &definition, team, unit, pos + offset);
+                    }
+                }
+            }
+
+            *cooldown = ability.cooldown();
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use bevy::{ecs::system::SystemParam, prelude::*, reflect::TypePath, utils::HashMap};
+use serde::Deserialize;
+
+use crate::data::Validate;
+
+use super::{
+    ai::{AttackCooldown, AttackDamage, AttackRange, Dead, MovementSpeed, MovementStyle, UnitRole},
+    boss::{Boss, BossAbility, BossDefinition},
+    damage::{Armor, DamageRoll, DamageType, Penetration, Resistances},
+    knockback::Knockback,
+    modifiers::{SquadModifiers, StatModifiers},
+    projectile::{ProjectileDefinition, RangedAttack},
+    squad::UnitType,
+    status::{ItemStatusEffects, OnHit, StatusEffect},
+    targeting::{TargetingOverrides, TargetingStrategy},
+    Team,
+};
+
+const CATALOG_PATH: &str = "data/base.units.ron";
+
+/// Every unit type, keyed by id.
+#[derive(Asset, TypePath, Deserialize)]
+#[serde(transparent)]
+pub struct UnitCatalog(pub HashMap<UnitType, UnitDefinition>);
+
+#[derive(Clone, Deserialize)]
+pub struct UnitDefinition {
+    #[serde(default)]
+    pub role: UnitRole,
+    pub health: f32,
+    pub damage: f32,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    /// Hits deal up to this fraction more or less than `damage`
+    #[serde(default)]
+    pub damage_variance: f32,
+    /// Chance of a hit being critical, from 0 to 1
+    #[serde(default)]
+    pub crit_chance: f32,
+    #[serde(default = "default_crit_multiplier")]
+    pub crit_multiplier: f32,
+    #[serde(default)]
+    pub armor: f32,
+    /// Amount of the target's armor that attacks ignore
+    #[serde(default)]
+    pub penetration: f32,
+    #[serde(default)]
+    pub resistances: Resistances,
+    /// Impulse applied per point of damage dealt, scaled by density
+    #[serde(default)]
+    pub knockback: f32,
+    pub range: f32,
+    /// Seconds between attacks
+    pub cooldown: f32,
+    pub speed: f32,
+    pub movement: MovementStyle,
+    #[serde(default)]
+    pub targeting: TargetingStrategy,
+    pub collider_radius: f32,
+    pub density: f32,
+    /// Distance between units in a formation
+    pub spacing: (f32, f32),
+    pub sprite: UnitSpriteDefinition,
+    pub sounds: UnitSoundDefinition,
+    /// Ranged units fire projectiles instead of hitting their target directly
+    #[serde(default)]
+    pub projectile: Option<ProjectileDefinition>,
+    /// Status effects applied to units this unit hits
+    #[serde(default)]
+    pub on_hit: Vec<StatusEffect>,
+    /// Makes this unit a boss, with abilities that change as it loses health
+    #[serde(default)]
+    pub boss: Option<BossDefinition>,
+}
+
+fn default_crit_multiplier() -> f32 {
+    2.0
+}
+
+#[derive(Clone, Deserialize)]
+pub struct UnitSpriteDefinition {
+    pub friendly: String,
+    pub enemy: String,
+    /// Size of a single frame
+    pub size: (f32, f32),
+    /// Number of frames in the attack animation, laid out in a single row
+    pub frames: usize,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct UnitSoundDefinition {
+    pub attack: String,
+    pub death: String,
+}
+
+impl UnitDefinition {
+    pub fn damage_roll(&self) -> DamageRoll {
+        DamageRoll {
+            variance: self.damage_variance,
+            crit_chance: self.crit_chance,
+            crit_multiplier: self.crit_multiplier,
+        }
+    }
+}
+
+impl Validate for UnitCatalog {
+    fn validate(&self) -> Result<(), String> {
+        if self.0.is_empty() {
+            return Err("no units defined".to_string());
+        }
+
+        for (unit, definition) in self.0.iter() {
+            definition
+                .validate()
+                .map_err(|e| format!("unit \"{}\": {}", unit.0, e))?;
+
+            let summons = definition
+                .boss
+                .iter()
+                .flat_map(|boss| boss.phases.iter())
+                .flat_map(|phase| phase.abilities.iter())
+                .filter_map(|ability| match ability {
+                    BossAbility::Summon { unit, .. } => Some(unit),
+                    _ => None,
+                });
+
+            for summon in summons {
+                match self.0.get(summon) {
+                    Some(summoned) if summoned.boss.is_none() => {}
+                    Some(_) => {
+                        return Err(format!(
+                            "unit \"{}\": can't summon boss \"{}\"",
+                            unit.0, summon.0
+                        ))
+                    }
+                    None => {
+                        return Err(format!(
+                            "unit \"{}\": summons unknown unit \"{}\"",
+                            unit.0, summon.0
+                        ))
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for UnitDefinition {
+    fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("health", self.health),
+            ("collider_radius", self.collider_radius),
+            ("density", self.density),
+            ("spacing.x", self.spacing.0),
+            ("spacing.y", self.spacing.1),
+            ("sprite.size.x", self.sprite.size.0),
+            ("sprite.size.y", self.sprite.size.1),
+        ];
+
+        for (name, value) in positive {
+            if value <= 0.0 {
+                return Err(format!("{} must be greater than 0, got {}", name, value));
+            }
+        }
+
+        let non_negative = [
+            ("damage", self.damage),
+            ("armor", self.armor),
+            ("penetration", self.penetration),
+            ("knockback", self.knockback),
+            ("range", self.range),
+            ("cooldown", self.cooldown),
+            ("speed", self.speed),
+        ];
+
+        for (name, value) in non_negative {
+            if value < 0.0 {
+                return Err(format!("{} must not be negative, got {}", name, value));
+            }
+        }
+
+        let fractions = [
+            ("damage_variance", self.damage_variance),
+            ("crit_chance", self.crit_chance),
+        ];
+
+        for (name, value) in fractions {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("{} must be between 0 and 1, got {}", name, value));
+            }
+        }
+
+        if self.crit_multiplier < 1.0 {
+            return Err(format!(
+                "crit_multiplier must be at least 1, got {}",
+                self.crit_multiplier
+            ));
+        }
+
+        for (damage_type, resistance) in self.resistances.0.iter() {
+            if *resistance > 1.0 {
+                return Err(format!(
+                    "{:?} resistance must be at most 1, got {}",
+                    damage_type, resistance
+                ));
+            }
+        }
+
+        if self.sprite.frames == 0 {
+            return Err("sprite.frames must be at least 1".to_string());
+        }
+
+        for effect in self.on_hit.iter() {
+            effect
+                .validate()
+                .map_err(|e| format!("on_hit {:?}: {}", effect.kind, e))?;
+        }
+
+        if self.role == UnitRole::Healer && self.projectile.is_some() {
+            return Err("healers can't fire projectiles".to_string());
+        }
+
+        if let Some(projectile) = &self.projectile {
+            projectile
+                .validate()
+                .map_err(|e| format!("projectile: {}", e))?;
+        }
+
+        if let Some(boss) = &self.boss {
+            boss.validate().map_err(|e| format!("boss: {}", e))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for ProjectileDefinition {
+    fn validate(&self) -> Result<(), String> {
+        if self.speed <= 0.0 {
+            return Err(format!("speed must be greater than 0, got {}", self.speed));
+        }
+
+        if self.hit_radius <= 0.0 {
+            return Err(format!(
+                "hit_radius must be greater than 0, got {}",
+                self.hit_radius
+            ));
+        }
+
+        if self.spread < 0.0 {
+            return Err(format!("spread must not be negative, got {}", self.spread));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+pub struct UnitCatalogHandle(pub Handle<UnitCatalog>);
+
+pub fn load_catalog(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(UnitCatalogHandle(asset_server.load(CATALOG_PATH)));
+}
+
+/// Looks up unit definitions in the loaded [`UnitCatalog`].
+#[derive(SystemParam)]
+pub struct UnitDefinitions<'w> {
+    handle: Option<Res<'w, UnitCatalogHandle>>,
+    catalogs: Res<'w, Assets<UnitCatalog>>,
+}
+
+impl<'w> UnitDefinitions<'w> {
+    pub fn catalog(&self) -> Option<&UnitCatalog> {
+        self.catalogs.get(&self.handle.as_ref()?.0)
+    }
+
+    pub fn get(&self, unit: &UnitType) -> Option<&UnitDefinition> {
+        self.catalog()?.0.get(unit)
+    }
+}
+
+pub fn catalog_loaded(definitions: UnitDefinitions) -> bool {
+    definitions.catalog().is_some()
+}
+
+/// Applies tuned stats to living units when the catalog is hot-reloaded.
+#[allow(clippy::too_many_arguments)]
+pub fn update_unit_stats(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<UnitCatalog>>,
+    definitions: UnitDefinitions,
+    modifiers: Res<StatModifiers>,
+    status_effects: Res<ItemStatusEffects>,
+    targeting: Res<TargetingOverrides>,
+    squads: Query<&SquadModifiers>,
+    mut units: Query<
+        (
+            Entity,
+            &UnitType,
+            &Team,
+            Option<&Parent>,
+            &mut AttackCooldown,
+            &mut AttackDamage,
+            &mut AttackRange,
+            &mut MovementSpeed,
+            &mut MovementStyle,
+            Option<&mut Boss>,
+        ),
+        Without<Dead>,
+    >,
+) {
+    if !events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }))
+    {
+        return;
+    }
+
+    info!("Unit catalog changed, updating units");
+
+    for (
+        ent,
+        unit,
+        team,
+        parent,
+        mut cooldown,
+        mut damage,
+        mut range,
+        mut speed,
+        mut style,
+        boss,
+    ) in units.iter_mut()
+    {
+        let definition = match definitions.get(unit) {
+            Some(definition) => definition,
+            None => continue,
+        };
+
+        let squad_modifiers = parent.and_then(|parent| squads.get(parent.get()).ok());
+        let definition = &modifiers.apply(definition, team, unit, squad_modifiers);
+
+        cooldown.0 = definition.cooldown;
+        damage.0 = definition.damage;
+        range.0 = definition.range;
+        speed.0 = definition.speed;
+        *style = definition.movement.clone();
+
+        commands.entity(ent).insert((
+            definition.role,
+            definition.damage_type,
+            Armor(definition.armor),
+            Penetration(definition.penetration),
+            Knockback(definition.knockback),
+            definition.damage_roll(),
+            definition.resistances.clone(),
+            targeting.resolve(&definition.targeting, team, unit),
+        ));
+
+        let on_hit = status_effects.on_hit(&definition.on_hit, team, unit);
+
+        match on_hit.0.is_empty() {
+            true => commands.entity(ent).remove::<OnHit>(),
+            false => commands.entity(ent).insert(on_hit),
+        };
+
+        match &definition.projectile {
+            Some(projectile) => commands.entity(ent).insert(RangedAttack::from(projectile)),
+            None => commands.entity(ent).remove::<RangedAttack>(),
+        };
+
+        match (&definition.boss, boss) {
+            (Some(definition), Some(mut boss)) => boss.reload(definition),
+            (Some(definition), None) => {
+                commands.entity(ent).insert(Boss::new(definition));
+            }
+            (None, Some(_)) => {
+                commands.entity(ent).remove::<Boss>();
+            }
+            (None, None) => {}
+        }
+    }
+}
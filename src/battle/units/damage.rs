@@ -0,0 +1,157 @@
+use bevy::{ecs::query::WorldQuery, prelude::*, utils::HashMap};
+use bevy_xpbd_2d::components::{Collider, ColliderAabb, Mass, RigidBody};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ai::{Dead, DeathEvent, Health, Movement},
+    knockback::Stagger,
+};
+
+/// Armor can never reduce a hit below this fraction of its damage.
+const MIN_DAMAGE: f32 = 0.1;
+
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DamageType {
+    #[default]
+    Slash,
+    Pierce,
+    Blunt,
+    /// Ignores armor
+    Magic,
+}
+
+/// Flat reduction to the damage of every non-magic hit.
+#[derive(Component, Clone, Default)]
+pub struct Armor(pub f32);
+
+/// Amount of the target's armor that attacks ignore.
+#[derive(Component, Clone, Default)]
+pub struct Penetration(pub f32);
+
+/// Fraction of damage of each type that is ignored, applied after armor.
+/// Negative values make a unit weak to that type.
+#[derive(Component, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Resistances(pub HashMap<DamageType, f32>);
+
+impl Resistances {
+    pub fn get(&self, damage_type: DamageType) -> f32 {
+        self.0.get(&damage_type).copied().unwrap_or(0.0)
+    }
+}
+
+/// How much the damage of a unit's hits varies.
+#[derive(Component, Clone, Default)]
+pub struct DamageRoll {
+    /// Hits deal up to this fraction more or less than the unit's damage
+    pub variance: f32,
+    /// Chance of a hit being critical, from 0 to 1
+    pub crit_chance: f32,
+    /// Damage multiplier of critical hits
+    pub crit_multiplier: f32,
+}
+
+impl DamageRoll {
+    /// Rolls the damage of a single hit, and whether it's critical.
+    pub fn roll(&self, damage: f32, rng: &mut impl Rng) -> (f32, bool) {
+        // Only draw when needed, so units without variance don't shift the stream
+        let damage = if self.variance > 0.0 {
+            damage * (1.0 + rng.gen_range(-self.variance..=self.variance))
+        } else {
+            damage
+        };
+
+        if self.crit_chance > 0.0 && rng.gen::<f32>() < self.crit_chance {
+            (damage * self.crit_multiplier, true)
+        } else {
+            (damage, false)
+        }
+    }
+}
+
+/// Sent whenever a hit damages a unit, with the damage left after its defenses.
+#[derive(Event)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub critical: bool,
+}
+
+/// A single hit, before the target's defenses are applied.
+#[derive(Clone, Copy)]
+pub struct Damage {
+    pub amount: f32,
+    pub damage_type: DamageType,
+    pub penetration: f32,
+    /// Impulse applied per point of damage dealt
+    pub knockback: f32,
+    pub critical: bool,
+}
+
+impl Damage {
+    pub fn against(&self, armor: Option<&Armor>, resistances: Option<&Resistances>) -> f32 {
+        let armor = match (self.damage_type, armor) {
+            (DamageType::Magic, _) | (_, None) => 0.0,
+            (_, Some(armor)) => (armor.0 - self.penetration).max(0.0),
+        };
+
+        let resistance = match resistances {
+            Some(resistances) => resistances.get(self.damage_type),
+            None => 0.0,
+        };
+
+        let amount = (self.amount - armor).max(self.amount * MIN_DAMAGE);
+
+        amount * (1.0 - resistance)
+    }
+}
+
+/// Everything needed to damage a unit.
+#[derive(WorldQuery)]
+#[world_query(mutable)]
+pub struct DamageTarget {
+    pub health: &'static mut Health,
+    pub armor: Option<&'static Armor>,
+    pub resistances: Option<&'static Resistances>,
+    pub stagger: Option<&'static mut Stagger>,
+    pub mass: Option<&'static Mass>,
+}
+
+/// Applies a hit to a unit after its defenses, killing it if its health runs out.
+/// The unit is knocked back along `direction`, which should be normalized or zero.
+/// Returns the damage dealt, which is zero if the unit was already dead.
+pub fn deal_damage(
+    commands: &mut Commands,
+    death_events: &mut EventWriter<DeathEvent>,
+    ent: Entity,
+    target: &mut DamageTargetItem,
+    damage: Damage,
+    direction: Vec2,
+) -> f32 {
+    if target.health.0 <= 0.0 {
+        return 0.0;
+    }
+
+    let amount = damage.against(target.armor, target.resistances);
+
+    target.health.0 -= amount;
+
+    if let (Some(stagger), Some(mass)) = (target.stagger.as_mut(), target.mass) {
+        stagger.hit(direction * amount * damage.knockback, mass.0);
+    }
+
+    if target.health.0 <= 0.0 {
+        death_events.send(DeathEvent { unit: ent });
+
+        commands
+            .entity(ent)
+            .insert(Dead)
+            // The broad phase keeps anything with an AABB, collider or not
+            .remove::<(Collider, ColliderAabb)>()
+            .remove::<Movement>()
+            .remove::<RigidBody>();
+    }
+
+    amount
+}
@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::menu::colors;
+
+use super::damage::DamageEvent;
+
+/// Most numbers shown at once, the oldest are reused past this.
+/// Hits past this within a single frame get no number.
+const MAX_NUMBERS: usize = 200;
+/// Seconds a number stays on screen.
+const LIFETIME: f32 = 0.8;
+/// World units per second numbers rise.
+const RISE_SPEED: f32 = 20.0;
+/// Height above the target numbers appear at.
+const OFFSET: f32 = 6.0;
+/// Text is rendered at a larger size and scaled down so it stays crisp.
+const SCALE: f32 = 0.25;
+const CRIT_SCALE: f32 = 0.4;
+
+#[derive(Component)]
+pub struct DamageNumber {
+    age: f32,
+}
+
+/// Damage number entities, reused instead of spawning one per hit.
+#[derive(Resource, Default)]
+pub struct DamageNumbers {
+    free: Vec<Entity>,
+    /// Oldest first
+    active: VecDeque<Entity>,
+}
+
+pub fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut pool: ResMut<DamageNumbers>,
+    mut events: EventReader<DamageEvent>,
+    asset_server: Res<AssetServer>,
+    targets: Query<&GlobalTransform>,
+    mut numbers: Query<(
+        &mut DamageNumber,
+        &mut Text,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    for event in events.read() {
+        if event.amount <= 0.0 {
+            continue;
+        }
+
+        let pos = match targets.get(event.target) {
+            Ok(transform) => transform.translation().truncate() + Vec2::Y * OFFSET,
+            Err(_) => continue,
+        };
+
+        let (color, scale) = if event.critical {
+            (Color::hex(colors::ACCENT).unwrap(), CRIT_SCALE)
+        } else {
+            (Color::WHITE, SCALE)
+        };
+
+        let text = Text::from_section(
+            format!("{:.0}", event.amount.max(1.0)),
+            TextStyle {
+                color,
+                font_size: 32.0,
+                font: asset_server.load("font/vt323.ttf"),
+            },
+        );
+        let transform =
+            Transform::from_translation(pos.extend(10.0)).with_scale(Vec3::splat(scale));
+
+        let reused = match pool.free.pop() {
+            Some(ent) => Some(ent),
+            None if pool.active.len() >= MAX_NUMBERS => {
+                // Numbers spawned this frame don't exist yet, so if even the oldest was,
+                // every number is in use and this hit goes without one
+                match pool.active.front() {
+                    Some(oldest) if numbers.contains(*oldest) => pool.active.pop_front(),
+                    _ => continue,
+                }
+            }
+            None => None,
+        };
+
+        let ent = match reused.and_then(|ent| numbers.get_mut(ent).ok().map(|n| (ent, n))) {
+            Some((ent, (mut number, mut number_text, mut number_transform, mut visibility))) => {
+                number.age = 0.0;
+                *number_text = text;
+                *number_transform = transform;
+                *visibility = Visibility::Visible;
+                ent
+            }
+            None => commands
+                .spawn((
+                    DamageNumber { age: 0.0 },
+                    Text2dBundle {
+                        text,
+                        transform,
+                        ..default()
+                    },
+                ))
+                .id(),
+        };
+
+        pool.active.push_back(ent);
+    }
+}
+
+pub fn animate_damage_numbers(
+    time: Res<Time>,
+    mut pool: ResMut<DamageNumbers>,
+    mut numbers: Query<(
+        &mut DamageNumber,
+        &mut Text,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    let delta = time.delta_seconds();
+
+    for (mut number, mut text, mut transform, visibility) in numbers.iter_mut() {
+        if *visibility == Visibility::Hidden {
+            continue;
+        }
+
+        number.age += delta;
+        transform.translation.y += RISE_SPEED * delta;
+
+        let alpha = (1.0 - number.age / LIFETIME).clamp(0.0, 1.0);
+        text.sections[0].style.color.set_a(alpha);
+    }
+
+    // Every number lives as long, so the oldest always expire first
+    while let Some(ent) = pool.active.front().copied() {
+        match numbers.get_mut(ent) {
+            Ok((number, _, _, mut visibility)) if number.age >= LIFETIME => {
+                *visibility = Visibility::Hidden;
+                pool.free.push(ent);
+            }
+            Ok(_) => break,
+            // Despawned by someone else
+            Err(_) => {}
+        }
+
+        pool.active.pop_front();
+    }
+}
+
+pub fn hide_damage_numbers(
+    mut pool: ResMut<DamageNumbers>,
+    mut numbers: Query<(Entity, &mut Visibility), With<DamageNumber>>,
+) {
+    pool.active.clear();
+    pool.free.clear();
+
+    for (ent, mut visibility) in numbers.iter_mut() {
+        *visibility = Visibility::Hidden;
+        pool.free.push(ent);
+    }
+}
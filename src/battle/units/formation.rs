@@ -1,7 +1,8 @@
 use bevy::prelude::*;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component, Clone, Default)]
+#[derive(Component, Clone, Default, Serialize, Deserialize)]
 pub enum Formation {
     #[default]
     Box,
@@ -69,8 +70,7 @@ fn coords_box(count: usize) -> Vec<(f32, f32)> {
     coords
 }
 
-pub fn rand_formation() -> Formation {
+pub fn rand_formation(rng: &mut impl Rng) -> Formation {
     let formations = [Formation::Pyramid, Formation::Box];
-    let mut rng = rand::thread_rng();
     formations[rng.gen_range(0..formations.len())].clone()
 }
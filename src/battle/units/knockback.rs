@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::components::LinearVelocity;
+
+/// Converts knockback impulses into the same scale as movement speeds.
+const KNOCKBACK_SCALE: f32 = 30.0;
+/// Fastest a unit can be knocked back.
+const MAX_STAGGER: f32 = 80.0;
+/// How quickly knockback wears off, per second.
+const STAGGER_DECAY: f32 = 8.0;
+
+/// Impulse a unit's hits apply per point of damage dealt.
+#[derive(Component, Clone, Default)]
+pub struct Knockback(pub f32);
+
+/// Velocity from being knocked back, on top of a unit's own movement.
+#[derive(Component, Clone, Default)]
+pub struct Stagger {
+    pub velocity: Vec2,
+    /// Part of the unit's current velocity that came from staggering
+    applied: Vec2,
+}
+
+impl Stagger {
+    /// Knocks the unit back, moving lighter units further.
+    pub fn hit(&mut self, impulse: Vec2, mass: f32) {
+        if mass <= 0.0 {
+            return;
+        }
+
+        self.velocity =
+            (self.velocity + impulse * KNOCKBACK_SCALE / mass).clamp_length_max(MAX_STAGGER);
+    }
+
+    /// Sets the unit's velocity to its own movement plus its stagger.
+    /// Without movement of its own, the unit keeps the velocity it already had.
+    pub fn apply(&mut self, velocity: &mut LinearVelocity, own: Option<Vec2>, delta: f32) {
+        let own = own.unwrap_or(velocity.0 - self.applied);
+
+        velocity.0 = own + self.velocity;
+
+        self.applied = self.velocity;
+        self.velocity *= (-STAGGER_DECAY * delta).exp();
+    }
+}
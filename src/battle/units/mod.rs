@@ -1,47 +1,140 @@
 use bevy::prelude::*;
+use bevy_xpbd_2d::{components::LinearVelocity, resources::SubstepCount};
+use serde::{Deserialize, Serialize};
 
-use crate::GameState;
+use crate::{data::RonLoader, GameState};
 
 pub mod ai;
 pub mod animation;
+pub mod boss;
+pub mod catalog;
+pub mod damage;
+mod damage_numbers;
 pub mod formation;
+pub mod knockback;
+pub mod modifiers;
+pub mod morale;
+pub mod orders;
+pub mod pathfinding;
 pub mod presets;
+pub mod projectile;
+mod shockwaves;
+pub mod sight;
 mod sounds;
+pub mod spatial;
 mod sprites;
 pub mod squad;
+pub mod status;
+pub mod steering;
+pub mod targeting;
+
+/// Unit targeting, movement and combat.
+/// Has no rendering or audio, so it can run headless.
+pub struct UnitsSimPlugin;
+
+/// The systems that fight out a battle, which [`UnitsPlugin`] only runs during [`GameState::Battle`].
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UnitsSimSet;
+
+impl Plugin for UnitsSimPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<catalog::UnitCatalog>()
+            .register_asset_loader(RonLoader::<catalog::UnitCatalog>::new(&["units.ron"]))
+            .add_event::<ai::AttackEvent>()
+            .add_event::<ai::DeathEvent>()
+            .add_event::<boss::SlamEvent>()
+            .add_event::<damage::DamageEvent>()
+            .init_resource::<modifiers::StatModifiers>()
+            .init_resource::<pathfinding::FlowFields>()
+            .init_resource::<pathfinding::NavGrid>()
+            .init_resource::<sight::FriendlyFire>()
+            .init_resource::<spatial::SpatialGrid>()
+            .init_resource::<status::ItemStatusEffects>()
+            .init_resource::<targeting::TargetingOverrides>()
+            // Units only push each other apart, which doesn't need the default twelve substeps
+            .insert_resource(SubstepCount(1))
+            .add_systems(Startup, catalog::load_catalog)
+            .add_systems(
+                Update,
+                (
+                    (
+                        spatial::update_spatial_grid,
+                        pathfinding::update_nav_grid,
+                        pathfinding::update_flow_fields,
+                        morale::update_morale,
+                        orders::update_squads,
+                        ai::set_target,
+                        sight::update_line_of_sight,
+                        ai::move_units,
+                        ai::attack,
+                        boss::update_bosses,
+                        projectile::move_projectiles,
+                        status::tick_status_effects,
+                    )
+                        .chain()
+                        .in_set(UnitsSimSet),
+                    catalog::update_unit_stats,
+                ),
+            );
+    }
+}
 
 pub struct UnitsPlugin;
 
 impl Plugin for UnitsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(sounds::SoundsPlugin)
+        app.add_plugins((UnitsSimPlugin, sounds::SoundsPlugin))
             .init_resource::<sprites::UnitSprites>()
-            .add_event::<animation::AttackEvent>()
-            .add_systems(Startup, sprites::load_sprites)
+            .init_resource::<damage_numbers::DamageNumbers>()
+            // Whatever is left of the armies stays put once the battle is decided
+            .configure_sets(Update, UnitsSimSet.run_if(in_state(GameState::Battle)))
             .add_systems(
                 OnEnter(GameState::Battle),
                 (despawn_units, squad::spawn_units),
             )
+            .add_systems(
+                OnExit(GameState::Battle),
+                (
+                    freeze_units,
+                    projectile::despawn_projectiles,
+                    shockwaves::despawn_shockwaves,
+                    damage_numbers::hide_damage_numbers,
+                ),
+            )
             .add_systems(
                 Update,
                 (
-                    (ai::set_target, ai::move_units, ai::attack).chain(),
                     animation::animate_atlas,
                     animation::animate_attack,
                     animation::flip_units,
-                    sprites::spawn_sprites,
+                    (sprites::reload_sprites, sprites::spawn_sprites).chain(),
+                    sprites::spawn_projectile_sprites,
+                    sprites::tint_status_effects,
+                    sprites::hide_dead_units,
+                    (shockwaves::spawn_shockwaves, shockwaves::animate_shockwaves),
+                    (
+                        damage_numbers::spawn_damage_numbers,
+                        damage_numbers::animate_damage_numbers,
+                    )
+                        .chain(),
                 ),
             );
     }
 }
 
-#[derive(Component, Clone, Debug, Default, PartialEq)]
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Team {
     #[default]
     Player,
     Enemy,
 }
 
+fn freeze_units(mut units: Query<&mut LinearVelocity, With<squad::Unit>>) {
+    for mut velocity in units.iter_mut() {
+        velocity.0 = Vec2::ZERO;
+    }
+}
+
 fn despawn_units(mut commands: Commands, units: Query<Entity, With<squad::Unit>>) {
     for ent in &mut units.iter() {
         commands.entity(ent).despawn_recursive();
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{catalog::UnitDefinition, squad::UnitType, Team};
+
+/// A unit stat that can be modified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Stat {
+    Armor,
+    Cooldown,
+    CritChance,
+    Damage,
+    Health,
+    Knockback,
+    Penetration,
+    Range,
+    SquadSize,
+    Speed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ModifierOp {
+    /// Added to the base value
+    Add(f32),
+    /// Multiplies the base value, after all additions
+    Multiply(f32),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Modifier {
+    pub stat: Stat,
+    pub op: ModifierOp,
+}
+
+impl Modifier {
+    /// Scales the effect of the modifier, so a factor of 0.5 makes it half as strong.
+    pub fn scaled(&self, factor: f32) -> Self {
+        let op = match self.op {
+            ModifierOp::Add(value) => ModifierOp::Add(value * factor),
+            ModifierOp::Multiply(value) => ModifierOp::Multiply((value - 1.0) * factor + 1.0),
+        };
+
+        Self {
+            stat: self.stat,
+            op,
+        }
+    }
+}
+
+/// A modifier for every unit of a team, or only the units of one type.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScopedModifier {
+    pub team: Team,
+    pub unit: Option<UnitType>,
+    pub modifier: Modifier,
+}
+
+/// Modifiers scoped to a team or unit type, collected over the run.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StatModifiers(pub Vec<ScopedModifier>);
+
+/// Modifiers that only apply to the units of a single squad.
+#[derive(Component, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SquadModifiers(pub Vec<Modifier>);
+
+impl StatModifiers {
+    /// Applies every modifier that affects `stat` for a unit of the given team, type and squad.
+    /// Additions are applied first, then multipliers.
+    pub fn resolve(
+        &self,
+        stat: Stat,
+        base: f32,
+        team: &Team,
+        unit: &UnitType,
+        squad: Option<&SquadModifiers>,
+    ) -> f32 {
+        let scoped = self
+            .0
+            .iter()
+            .filter(|scoped| scoped.team == *team)
+            .filter(|scoped| match &scoped.unit {
+                Some(scoped_unit) => scoped_unit == unit,
+                None => true,
+            })
+            .map(|scoped| &scoped.modifier);
+
+        let squad = squad.into_iter().flat_map(|squad| squad.0.iter());
+
+        let (add, multiply) = scoped
+            .chain(squad)
+            .filter(|modifier| modifier.stat == stat)
+            .fold((0.0, 1.0), |(add, multiply), modifier| match modifier.op {
+                ModifierOp::Add(value) => (add + value, multiply),
+                ModifierOp::Multiply(value) => (add, multiply * value),
+            });
+
+        ((base + add) * multiply).max(0.0)
+    }
+
+    /// A copy of the definition with all unit stats resolved.
+    pub fn apply(
+        &self,
+        definition: &UnitDefinition,
+        team: &Team,
+        unit: &UnitType,
+        squad: Option<&SquadModifiers>,
+    ) -> UnitDefinition {
+        let resolve = |stat, base| self.resolve(stat, base, team, unit, squad);
+
+        UnitDefinition {
+            armor: resolve(Stat::Armor, definition.armor),
+            cooldown: resolve(Stat::Cooldown, definition.cooldown),
+            crit_chance: resolve(Stat::CritChance, definition.crit_chance).min(1.0),
+            damage: resolve(Stat::Damage, definition.damage),
+            health: resolve(Stat::Health, definition.health),
+            knockback: resolve(Stat::Knockback, definition.knockback),
+            penetration: resolve(Stat::Penetration, definition.penetration),
+            range: resolve(Stat::Range, definition.range),
+            speed: resolve(Stat::Speed, definition.speed),
+            ..definition.clone()
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::battle::layout::ARENA_WIDTH;
+
+use super::{
+    ai::{Dead, DeathEvent},
+    orders::SquadState,
+    squad::Unit,
+    Team,
+};
+
+pub const MAX_MORALE: f32 = 100.0;
+/// Squads rout once their morale drops below this.
+const BREAK_MORALE: f32 = 30.0;
+/// Morale lost when the whole squad dies, spread over each death.
+const DEATH_PENALTY: f32 = MAX_MORALE;
+/// Distance within which squads affect each other's morale.
+const INFLUENCE_RADIUS: f32 = 80.0;
+/// Morale lost per second for each nearby routing ally.
+const PANIC_RATE: f32 = 15.0;
+/// Morale gained per second for each nearby strong ally.
+const RALLY_RATE: f32 = 3.0;
+/// Fraction of its units a squad needs to reassure its neighbours.
+const STRONG_SQUAD: f32 = 0.75;
+
+/// Willingness of a squad to keep fighting.
+#[derive(Component)]
+pub struct Morale {
+    pub value: f32,
+    pub broken: bool,
+}
+
+impl Default for Morale {
+    fn default() -> Self {
+        Self {
+            value: MAX_MORALE,
+            broken: false,
+        }
+    }
+}
+
+/// A unit whose squad has routed.
+/// It runs for its team's edge of the arena and no longer counts towards winning.
+#[derive(Component)]
+pub struct Fleeing;
+
+/// Where fleeing units of a team run to.
+pub fn team_edge(team: &Team) -> f32 {
+    match team {
+        Team::Player => -ARENA_WIDTH / 2.0,
+        Team::Enemy => ARENA_WIDTH / 2.0,
+    }
+}
+
+struct SquadSummary {
+    team: Team,
+    alive: usize,
+    center: Vec2,
+}
+
+pub fn update_morale(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut death_events: EventReader<DeathEvent>,
+    parents: Query<&Parent>,
+    mut squads: Query<(Entity, &mut Morale, &SquadState, &Children)>,
+    units: Query<(&Parent, &Team, &GlobalTransform), (With<Unit>, Without<Dead>)>,
+) {
+    for DeathEvent { unit } in death_events.read() {
+        let squad = match parents.get(*unit) {
+            Ok(parent) => parent.get(),
+            Err(_) => continue,
+        };
+
+        if let Ok((_, mut morale, state, _)) = squads.get_mut(squad) {
+            morale.value -= DEATH_PENALTY / state.spawned.max(1) as f32;
+        }
+    }
+
+    let mut summaries = HashMap::<Entity, SquadSummary>::default();
+
+    for (parent, team, transform) in units.iter() {
+        let summary = summaries
+            .entry(parent.get())
+            .or_insert_with(|| SquadSummary {
+                team: team.clone(),
+                alive: 0,
+                center: Vec2::ZERO,
+            });
+
+        summary.alive += 1;
+        summary.center += transform.translation().truncate();
+    }
+
+    for summary in summaries.values_mut() {
+        summary.center /= summary.alive as f32;
+    }
+
+    // Neighbours are judged by how they were doing at the start of the frame
+    let neighbours = squads
+        .iter()
+        .filter_map(|(ent, morale, state, _)| {
+            let summary = summaries.get(&ent)?;
+            let strong = summary.alive as f32 / state.spawned.max(1) as f32 >= STRONG_SQUAD;
+
+            Some((ent, morale.broken, strong))
+        })
+        .collect::<Vec<_>>();
+
+    let delta = time.delta_seconds();
+
+    for (ent, mut morale, _, children) in squads.iter_mut() {
+        if morale.broken {
+            continue;
+        }
+
+        let summary = match summaries.get(&ent) {
+            Some(summary) => summary,
+            None => continue,
+        };
+
+        for (other, broken, strong) in neighbours.iter() {
+            let other_summary = match summaries.get(other) {
+                Some(other_summary) => other_summary,
+                None => continue,
+            };
+
+            if *other == ent
+                || other_summary.team != summary.team
+                || other_summary.center.distance(summary.center) > INFLUENCE_RADIUS
+            {
+                continue;
+            }
+
+            if *broken {
+                morale.value -= PANIC_RATE * delta;
+            } else if *strong {
+                morale.value += RALLY_RATE * delta;
+            }
+        }
+
+        morale.value = morale.value.clamp(0.0, MAX_MORALE);
+
+        if morale.value < BREAK_MORALE {
+            info!("Squad {:?} routed", ent);
+            morale.broken = true;
+
+            for child in children.iter() {
+                if units.contains(*child) {
+                    commands.entity(*child).insert(Fleeing);
+                }
+            }
+        }
+    }
+}
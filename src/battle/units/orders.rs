@@ -0,0 +1,271 @@
+use bevy::{prelude::*, utils::HashMap};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::battle::layout::ARENA_HEIGHT;
+
+use super::{
+    ai::{AttackRange, Dead, Health, MaxHealth},
+    morale::Morale,
+    spatial::SpatialGrid,
+    Team,
+};
+
+/// How close to the arena edge flanking squads move.
+const FLANK_MARGIN: f32 = 40.0;
+/// How close a squad has to get to where it's going to have arrived.
+pub const ARRIVE_RADIUS: f32 = 4.0;
+/// How far beyond their attack range units notice enemies while holding or flanking.
+const ENGAGE_MARGIN: f32 = 30.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SquadOrder {
+    /// Attacks right away
+    #[default]
+    Charge,
+    /// Stays in place until enemies get close or it takes damage
+    Hold,
+    /// Moves along the top edge of the arena before attacking
+    FlankTop,
+    /// Moves along the bottom edge of the arena before attacking
+    FlankBottom,
+}
+
+impl SquadOrder {
+    pub const ALL: [SquadOrder; 4] = [
+        SquadOrder::Charge,
+        SquadOrder::Hold,
+        SquadOrder::FlankTop,
+        SquadOrder::FlankBottom,
+    ];
+
+    /// Orders that go after the enemy on their own.
+    /// The enemy only picks from these, if both armies held the battle would never end.
+    pub const ATTACKING: [SquadOrder; 3] = [
+        SquadOrder::Charge,
+        SquadOrder::FlankTop,
+        SquadOrder::FlankBottom,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SquadOrder::Charge => "Charge",
+            SquadOrder::Hold => "Hold",
+            SquadOrder::FlankTop => "Flank top",
+            SquadOrder::FlankBottom => "Flank bottom",
+        }
+    }
+
+    /// Where the squad moves to before attacking, if anywhere.
+    pub fn waypoint(&self) -> Option<Vec2> {
+        let edge = ARENA_HEIGHT / 2.0 - FLANK_MARGIN;
+
+        match self {
+            SquadOrder::FlankTop => Some(Vec2::new(0.0, edge)),
+            SquadOrder::FlankBottom => Some(Vec2::new(0.0, -edge)),
+            SquadOrder::Charge | SquadOrder::Hold => None,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|order| order == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Orders a squad follows during the next battle.
+#[derive(Component, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SquadOrders {
+    pub order: SquadOrder,
+    /// Fraction of its units below which the squad falls back to where it started
+    #[serde(default)]
+    pub retreat_below: Option<f32>,
+}
+
+impl SquadOrders {
+    /// Retreat thresholds players can pick from.
+    pub const RETREAT_THRESHOLDS: [Option<f32>; 3] = [None, Some(0.5), Some(0.25)];
+
+    pub fn next_retreat(&self) -> Option<f32> {
+        let index = Self::RETREAT_THRESHOLDS
+            .iter()
+            .position(|threshold| *threshold == self.retreat_below)
+            .unwrap_or(0);
+
+        Self::RETREAT_THRESHOLDS[(index + 1) % Self::RETREAT_THRESHOLDS.len()]
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self {
+            order: SquadOrder::ATTACKING[rng.gen_range(0..SquadOrder::ATTACKING.len())],
+            retreat_below: Self::RETREAT_THRESHOLDS
+                [rng.gen_range(0..Self::RETREAT_THRESHOLDS.len())],
+        }
+    }
+}
+
+impl std::fmt::Display for SquadOrders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.retreat_below {
+            Some(threshold) => write!(
+                f,
+                "{}, retreat at {}%",
+                self.order.name(),
+                (threshold * 100.0).round()
+            ),
+            None => write!(f, "{}", self.order.name()),
+        }
+    }
+}
+
+/// Where a unit stands in its squad's formation, relative to the squad.
+#[derive(Component, Clone, Copy)]
+pub struct FormationOffset(pub Vec2);
+
+/// What a squad's units are currently doing.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SquadBehavior {
+    /// Every unit picks its own target
+    #[default]
+    Engage,
+    /// Units keep formation around this point, without attacking
+    MoveTo(Vec2),
+    /// Units run for their team's edge of the arena, without attacking
+    Rout,
+}
+
+/// Progress of a squad through its orders during a battle.
+#[derive(Component, Default)]
+pub struct SquadState {
+    pub spawned: usize,
+    pub engaged: bool,
+    pub retreating: bool,
+    /// Retreated all the way and turned to fight, which it keeps doing
+    pub cornered: bool,
+    pub behavior: SquadBehavior,
+    /// Where the squad's formation is centered, from where its units are
+    pub anchor: Vec2,
+}
+
+impl SquadState {
+    pub fn new(spawned: usize) -> Self {
+        Self {
+            spawned,
+            ..default()
+        }
+    }
+}
+
+#[derive(Default)]
+struct SquadSummary {
+    alive: usize,
+    /// Sum of where each unit thinks the squad is, from its formation offset
+    anchor: Vec2,
+    threatened: bool,
+    wounded: bool,
+}
+
+pub fn update_squads(
+    grid: Res<SpatialGrid>,
+    mut squads: Query<(
+        Entity,
+        &mut SquadState,
+        &GlobalTransform,
+        Option<&SquadOrders>,
+        Option<&Morale>,
+    )>,
+    units: Query<
+        (
+            &Parent,
+            &Team,
+            &GlobalTransform,
+            &FormationOffset,
+            &AttackRange,
+            &Health,
+            &MaxHealth,
+        ),
+        Without<Dead>,
+    >,
+) {
+    let mut summaries = HashMap::<Entity, SquadSummary>::default();
+
+    for (parent, team, transform, offset, range, health, max_health) in units.iter() {
+        let pos = transform.translation().truncate();
+        let summary = summaries.entry(parent.get()).or_default();
+
+        summary.alive += 1;
+        summary.anchor += pos - offset.0;
+        summary.wounded |= health.0 < max_health.0;
+
+        // One unit seeing an enemy is enough for the whole squad
+        if !summary.threatened {
+            summary.threatened = grid
+                .enemies_within(team, pos, range.0 + ENGAGE_MARGIN)
+                .next()
+                .is_some();
+        }
+    }
+
+    for (ent, mut state, transform, orders, morale) in squads.iter_mut() {
+        let summary = match summaries.get(&ent) {
+            Some(summary) => summary,
+            None => continue,
+        };
+
+        let orders = orders.cloned().unwrap_or_default();
+        let rally = transform.translation().truncate();
+        let anchor = summary.anchor / summary.alive as f32;
+
+        state.anchor = anchor;
+
+        if let Some(threshold) = orders.retreat_below {
+            let strength = summary.alive as f32 / state.spawned.max(1) as f32;
+
+            if !state.retreating && strength < threshold {
+                info!("Squad {:?} is retreating", ent);
+                state.retreating = true;
+            }
+        }
+
+        let routed = match morale {
+            Some(morale) => morale.broken,
+            None => false,
+        };
+
+        let behavior = if routed {
+            SquadBehavior::Rout
+        } else if state.retreating {
+            // Fight back once there is nowhere left to run, even if the fight pulls it away
+            if state.cornered || (summary.threatened && anchor.distance(rally) <= ARRIVE_RADIUS) {
+                state.cornered = true;
+                SquadBehavior::Engage
+            } else {
+                SquadBehavior::MoveTo(rally)
+            }
+        } else if state.engaged || summary.threatened || summary.wounded {
+            state.engaged = true;
+            SquadBehavior::Engage
+        } else {
+            match orders.order {
+                SquadOrder::Charge => SquadBehavior::Engage,
+                SquadOrder::Hold => SquadBehavior::MoveTo(rally),
+                SquadOrder::FlankTop | SquadOrder::FlankBottom => match orders.order.waypoint() {
+                    Some(waypoint) if anchor.distance(waypoint) > ARRIVE_RADIUS => {
+                        SquadBehavior::MoveTo(waypoint)
+                    }
+                    _ => {
+                        state.engaged = true;
+                        SquadBehavior::Engage
+                    }
+                },
+            }
+        };
+
+        if state.behavior != behavior {
+            state.behavior = behavior;
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::battle::{
+    layout::{ARENA_HEIGHT, ARENA_WIDTH},
+    terrain::TerrainFeature,
+};
+
+/// Width and height of a navigation cell, in world units.
+const CELL_SIZE: f32 = 8.0;
+/// Distance units keep from obstacles, about the radius of a unit.
+const OBSTACLE_MARGIN: f32 = 3.0;
+/// Cost of moving to a neighbouring cell, before terrain.
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+/// Seconds a flow field is kept after the last unit stopped following it.
+const FIELD_LIFETIME: f32 = 1.0;
+/// How many cells a target can move away from the goal of a field before it gets a new one.
+const REUSE_CELLS: i32 = 2;
+/// Most flow fields built in a single frame.
+const MAX_BUILDS: usize = 8;
+
+const NEIGHBOURS: [(IVec2, u32); 8] = [
+    (IVec2::new(1, 0), STRAIGHT_COST),
+    (IVec2::new(-1, 0), STRAIGHT_COST),
+    (IVec2::new(0, 1), STRAIGHT_COST),
+    (IVec2::new(0, -1), STRAIGHT_COST),
+    (IVec2::new(1, 1), DIAGONAL_COST),
+    (IVec2::new(1, -1), DIAGONAL_COST),
+    (IVec2::new(-1, 1), DIAGONAL_COST),
+    (IVec2::new(-1, -1), DIAGONAL_COST),
+];
+
+/// The arena divided into cells, with the cost of crossing each.
+/// Rebuilt whenever the terrain changes by [`update_nav_grid`].
+#[derive(Resource)]
+pub struct NavGrid {
+    size: IVec2,
+    origin: Vec2,
+    /// Cost multiplier of each cell, `None` if it's blocked
+    costs: Vec<Option<f32>>,
+    blocked: bool,
+}
+
+impl Default for NavGrid {
+    fn default() -> Self {
+        let size = (Vec2::new(ARENA_WIDTH, ARENA_HEIGHT) / CELL_SIZE)
+            .ceil()
+            .as_ivec2();
+
+        Self {
+            size,
+            origin: -Vec2::new(ARENA_WIDTH, ARENA_HEIGHT) / 2.0,
+            costs: vec![Some(1.0); (size.x * size.y) as usize],
+            blocked: false,
+        }
+    }
+}
+
+impl NavGrid {
+    fn cell(&self, pos: Vec2) -> IVec2 {
+        ((pos - self.origin) / CELL_SIZE)
+            .floor()
+            .as_ivec2()
+            .clamp(IVec2::ZERO, self.size - 1)
+    }
+
+    fn center(&self, cell: IVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + 0.5) * CELL_SIZE
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        if cell.cmplt(IVec2::ZERO).any() || cell.cmpge(self.size).any() {
+            return None;
+        }
+
+        Some((cell.y * self.size.x + cell.x) as usize)
+    }
+
+    fn cost(&self, cell: IVec2) -> Option<f32> {
+        self.costs[self.index(cell)?]
+    }
+
+    /// Whether `pos` is inside an obstacle, or too close to one.
+    pub fn is_blocked(&self, pos: Vec2) -> bool {
+        self.blocked && self.cost(self.cell(pos)).is_none()
+    }
+
+    /// Whether a unit can walk straight from `from` to `to` without hitting an obstacle.
+    pub fn line_clear(&self, from: Vec2, to: Vec2) -> bool {
+        if !self.blocked {
+            return true;
+        }
+
+        let steps = (from.distance(to) / (CELL_SIZE / 2.0)).ceil() as usize;
+
+        (0..=steps).all(|step| {
+            let pos = from.lerp(to, step as f32 / steps.max(1) as f32);
+            self.cost(self.cell(pos)).is_some()
+        })
+    }
+}
+
+/// Distance from each cell to a goal cell, going around obstacles.
+pub struct FlowField {
+    distances: Vec<u32>,
+    /// Seconds since a unit last followed the field
+    idle: f32,
+}
+
+impl FlowField {
+    fn build(grid: &NavGrid, goal: usize) -> Self {
+        let mut distances = vec![u32::MAX; grid.costs.len()];
+        let mut queue = BinaryHeap::new();
+
+        distances[goal] = 0;
+        queue.push(Reverse((0, goal)));
+
+        while let Some(Reverse((distance, index))) = queue.pop() {
+            if distance > distances[index] {
+                continue;
+            }
+
+            let cell = IVec2::new(index as i32 % grid.size.x, index as i32 / grid.size.x);
+
+            for (offset, step) in NEIGHBOURS {
+                let neighbour = cell + offset;
+
+                let (neighbour_index, cost) = match grid.index(neighbour) {
+                    Some(i) => match grid.costs[i] {
+                        Some(cost) => (i, cost),
+                        None => continue,
+                    },
+                    None => continue,
+                };
+
+                // Don't cut corners past obstacles
+                if offset.x != 0
+                    && offset.y != 0
+                    && (grid.cost(cell + IVec2::new(offset.x, 0)).is_none()
+                        || grid.cost(cell + IVec2::new(0, offset.y)).is_none())
+                {
+                    continue;
+                }
+
+                let next = distance + (step as f32 * cost).round() as u32;
+
+                if next < distances[neighbour_index] {
+                    distances[neighbour_index] = next;
+                    queue.push(Reverse((next, neighbour_index)));
+                }
+            }
+        }
+
+        Self {
+            distances,
+            idle: 0.0,
+        }
+    }
+
+    /// Direction to move in from `pos` to get closer to the goal,
+    /// or `None` if already there or the goal can't be reached.
+    fn direction(&self, grid: &NavGrid, pos: Vec2) -> Option<Vec2> {
+        let cell = grid.cell(pos);
+        let current = *self.distances.get(grid.index(cell)?)?;
+
+        if current == 0 {
+            return None;
+        }
+
+        let (best, distance) = NEIGHBOURS
+            .iter()
+            .filter_map(|(offset, _)| {
+                let neighbour = cell + *offset;
+                let distance = *self.distances.get(grid.index(neighbour)?)?;
+                Some((neighbour, distance))
+            })
+            .min_by_key(|(_, distance)| *distance)?;
+
+        if distance >= current {
+            return None;
+        }
+
+        Some((grid.center(best) - pos).normalize_or_zero())
+    }
+}
+
+/// Flow fields towards the targets and destinations of units that can't walk straight there,
+/// keyed by the cell they lead to.
+/// Built the first time a unit needs one, and dropped once no unit has followed them
+/// for [`FIELD_LIFETIME`] by [`update_flow_fields`].
+#[derive(Resource, Default)]
+pub struct FlowFields {
+    fields: HashMap<IVec2, FlowField>,
+    /// Fields that can still be built this frame
+    builds_left: usize,
+}
+
+impl FlowFields {
+    /// Direction for a unit at `pos` to take towards `target`.
+    /// Units head straight for targets they can reach in a line, and follow a flow field otherwise.
+    pub fn direction(&mut self, grid: &NavGrid, pos: Vec2, target: Vec2) -> Vec2 {
+        let straight = (target - pos).normalize_or_zero();
+
+        if grid.line_clear(pos, target) {
+            return straight;
+        }
+
+        let goal = match self.reusable(grid, target) {
+            Some(goal) => goal,
+            None if self.builds_left > 0 => {
+                let goal = grid.cell(target);
+
+                self.builds_left -= 1;
+                self.fields
+                    .insert(goal, FlowField::build(grid, grid.index(goal).unwrap()));
+
+                goal
+            }
+            // Steering keeps the unit off obstacles until a field can be built for it
+            None => return straight,
+        };
+
+        let field = self.fields.get_mut(&goal).unwrap();
+        field.idle = 0.0;
+
+        field.direction(grid, pos).unwrap_or(straight)
+    }
+
+    /// Goal of the closest field that leads to within sight of `target`,
+    /// so units chasing the same or a nearby target share a field as it moves.
+    fn reusable(&self, grid: &NavGrid, target: Vec2) -> Option<IVec2> {
+        let cell = grid.cell(target);
+
+        (-REUSE_CELLS..=REUSE_CELLS)
+            .flat_map(|x| (-REUSE_CELLS..=REUSE_CELLS).map(move |y| cell + IVec2::new(x, y)))
+            .filter(|goal| self.fields.contains_key(goal))
+            .filter(|goal| *goal == cell || grid.line_clear(grid.center(*goal), target))
+            .min_by_key(|goal| (*goal - cell).length_squared())
+    }
+}
+
+pub fn update_nav_grid(
+    mut grid: ResMut<NavGrid>,
+    mut fields: ResMut<FlowFields>,
+    features: Query<&TerrainFeature>,
+    added: Query<(), Added<TerrainFeature>>,
+    mut removed: RemovedComponents<TerrainFeature>,
+) {
+    let removed = removed.read().count() > 0;
+
+    if added.is_empty() && !removed {
+        return;
+    }
+
+    let mut new_grid = NavGrid::default();
+
+    for feature in features.iter() {
+        let center = Vec2::from(feature.position);
+        let speed_factor = feature.kind.speed_factor();
+        let shape = match speed_factor {
+            Some(_) => feature.shape,
+            None => feature.shape.inflated(OBSTACLE_MARGIN),
+        };
+
+        let half_size = shape.size() / 2.0;
+        let min = new_grid.cell(center - half_size);
+        let max = new_grid.cell(center + half_size);
+
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let cell = IVec2::new(x, y);
+
+                if !shape.contains(new_grid.center(cell) - center) {
+                    continue;
+                }
+
+                let index = new_grid.index(cell).unwrap();
+
+                new_grid.costs[index] = match (speed_factor, new_grid.costs[index]) {
+                    (None, _) | (_, None) => None,
+                    // Slower ground costs more to cross
+                    (Some(speed_factor), Some(cost)) => Some(cost.max(1.0 / speed_factor)),
+                };
+            }
+        }
+    }
+
+    new_grid.blocked = new_grid.costs.iter().any(|cost| cost.is_none());
+
+    *grid = new_grid;
+
+    // The flow fields lead around the old terrain
+    fields.fields.clear();
+}
+
+/// Drops flow fields no unit has followed for [`FIELD_LIFETIME`],
+/// and lets up to [`MAX_BUILDS`] new ones be built this frame.
+pub fn update_flow_fields(time: Res<Time>, mut fields: ResMut<FlowFields>) {
+    let delta = time.delta_seconds();
+
+    fields.builds_left = MAX_BUILDS;
+    fields.fields.retain(|_, field| {
+        field.idle += delta;
+        field.idle < FIELD_LIFETIME
+    });
+}
@@ -1,50 +1,69 @@
 use bevy::prelude::*;
 use bevy_xpbd_2d::prelude::*;
 
-use super::ai::{AttackCooldown, AttackDamage, AttackRange, Health, MovementSpeed, MovementStyle};
+use super::{
+    ai::{
+        AttackCooldown, AttackDamage, AttackRange, Health, MaxHealth, MovementSpeed, MovementStyle,
+        Retarget, UnitRole,
+    },
+    catalog::UnitDefinition,
+    damage::{Armor, DamageRoll, DamageType, Penetration, Resistances},
+    knockback::{Knockback, Stagger},
+    status::StatusEffects,
+    targeting::TargetingStrategy,
+};
 
 #[derive(Bundle, Clone)]
 pub struct UnitBundle {
+    pub armor: Armor,
     pub attack_speed: AttackCooldown,
     pub collider: Collider,
     pub damage: AttackDamage,
+    pub damage_roll: DamageRoll,
+    pub damage_type: DamageType,
     pub density: ColliderDensity,
     pub health: Health,
+    pub knockback: Knockback,
     pub locked: LockedAxes,
+    pub max_health: MaxHealth,
     pub movement_speed: MovementSpeed,
     pub movement_style: MovementStyle,
+    pub penetration: Penetration,
     pub range: AttackRange,
+    pub resistances: Resistances,
+    pub retarget: Retarget,
     pub rigid_body: RigidBody,
+    pub role: UnitRole,
+    pub stagger: Stagger,
+    pub status: StatusEffects,
+    pub targeting: TargetingStrategy,
 }
 
 impl UnitBundle {
-    pub fn knight() -> Self {
+    pub fn new(definition: &UnitDefinition) -> Self {
         Self {
-            attack_speed: AttackCooldown(1.0),
-            collider: Collider::ball(3.0),
-            damage: AttackDamage(20.0),
-            density: ColliderDensity(1.0),
-            health: Health(100.0),
+            armor: Armor(definition.armor),
+            attack_speed: AttackCooldown(definition.cooldown),
+            collider: Collider::ball(definition.collider_radius),
+            damage: AttackDamage(definition.damage),
+            damage_roll: definition.damage_roll(),
+            damage_type: definition.damage_type,
+            density: ColliderDensity(definition.density),
+            health: Health(definition.health),
+            knockback: Knockback(definition.knockback),
             locked: LockedAxes::ROTATION_LOCKED,
-            movement_speed: MovementSpeed(15.0),
-            movement_style: MovementStyle::WithinRange,
-            range: AttackRange(9.0),
-            rigid_body: RigidBody::Dynamic,
-        }
-    }
-
-    pub fn archer() -> Self {
-        Self {
-            attack_speed: AttackCooldown(2.0),
-            collider: Collider::ball(2.0),
-            damage: AttackDamage(5.0),
-            density: ColliderDensity(0.75),
-            health: Health(50.0),
-            locked: LockedAxes::ROTATION_LOCKED,
-            movement_speed: MovementSpeed(10.0),
-            movement_style: MovementStyle::WithinRange,
-            range: AttackRange(50.0),
+            max_health: MaxHealth(definition.health),
+            movement_speed: MovementSpeed(definition.speed),
+            movement_style: definition.movement.clone(),
+            penetration: Penetration(definition.penetration),
+            range: AttackRange(definition.range),
+            resistances: definition.resistances.clone(),
+            retarget: Retarget::default(),
             rigid_body: RigidBody::Dynamic,
+            role: definition.role,
+            stagger: Stagger::default(),
+            status: StatusEffects::default(),
+            targeting: definition.targeting.clone(),
         }
     }
 }
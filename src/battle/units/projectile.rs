@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use serde::Deserialize;
+
+use crate::battle::terrain::TerrainFeature;
+
+use super::{
+    ai::{Dead, DeathEvent},
+    damage::{deal_damage, Damage, DamageEvent, DamageTarget},
+    sight::FriendlyFire,
+    spatial::SpatialGrid,
+    squad::{Unit, UnitType},
+    status::{OnHit, StatusEffects},
+    Team,
+};
+
+/// How far past its attack range a projectile flies before it is considered a miss.
+const OVERSHOOT: f32 = 1.5;
+/// Colliders a projectile looks through each frame for an obstacle, past the units in front of it.
+const OBSTACLE_HITS: u32 = 4;
+
+#[derive(Clone, Deserialize)]
+pub struct ProjectileDefinition {
+    pub speed: f32,
+    /// Maximum angle in radians a shot can stray from its target
+    pub spread: f32,
+    /// How close a projectile has to pass by a unit to hit it
+    pub hit_radius: f32,
+    pub sprite: String,
+}
+
+/// Attacks by firing projectiles instead of damaging the target directly.
+#[derive(Component, Clone)]
+pub struct RangedAttack {
+    pub speed: f32,
+    pub spread: f32,
+    pub hit_radius: f32,
+}
+
+impl From<&ProjectileDefinition> for RangedAttack {
+    fn from(definition: &ProjectileDefinition) -> Self {
+        Self {
+            speed: definition.speed,
+            spread: definition.spread,
+            hit_radius: definition.hit_radius,
+        }
+    }
+}
+
+/// A projectile in flight.
+/// Hits the first unit of another team that it passes by, which is not necessarily
+/// the unit it was aimed at, or an ally with [`FriendlyFire`]. Obstacles stop it.
+#[derive(Component)]
+pub struct Projectile {
+    /// Unit that fired it, which it never hits
+    pub shooter: Entity,
+    pub team: Team,
+    /// Type of the unit that fired it
+    pub unit: UnitType,
+    pub damage: Damage,
+    pub velocity: Vec2,
+    pub hit_radius: f32,
+    /// Distance left before the projectile falls to the ground
+    pub remaining: f32,
+}
+
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    pub projectile: Projectile,
+    pub transform: TransformBundle,
+    pub visibility: VisibilityBundle,
+}
+
+impl ProjectileBundle {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        shooter: Entity,
+        team: Team,
+        unit: UnitType,
+        attack: &RangedAttack,
+        damage: Damage,
+        range: f32,
+        from: Vec2,
+        direction: Vec2,
+    ) -> Self {
+        let velocity = direction * attack.speed;
+        let transform = Transform::from_translation(from.extend(1.0))
+            .with_rotation(Quat::from_rotation_z(velocity.y.atan2(velocity.x)));
+
+        Self {
+            projectile: Projectile {
+                shooter,
+                team,
+                unit,
+                damage,
+                velocity,
+                hit_radius: attack.hit_radius,
+                remaining: range * OVERSHOOT,
+            },
+            transform: TransformBundle::from_transform(transform),
+            visibility: VisibilityBundle::default(),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    friendly_fire: Res<FriendlyFire>,
+    grid: Res<SpatialGrid>,
+    spatial: SpatialQuery,
+    mut death_events: EventWriter<DeathEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform, Option<&OnHit>)>,
+    mut units: Query<DamageTarget, (With<Unit>, Without<Dead>)>,
+    mut statuses: Query<&mut StatusEffects>,
+    terrain: Query<(), With<TerrainFeature>>,
+) {
+    for (ent, mut projectile, mut transform, on_hit) in projectiles.iter_mut() {
+        let start = transform.translation.truncate();
+        let step = projectile.velocity * time.delta_seconds();
+        let length = step.length();
+        let direction = step.normalize_or_zero();
+
+        // Only units around the path travelled this frame can be hit
+        let center = start + step / 2.0;
+        let radius = length / 2.0 + projectile.hit_radius;
+        let allies = match friendly_fire.0 {
+            true => Some(grid.allies_within(&projectile.team, center, radius)),
+            false => None,
+        };
+
+        // Find the first unit along the path travelled this frame
+        let hit = grid
+            .enemies_within(&projectile.team, center, radius)
+            .chain(allies.into_iter().flatten())
+            .filter(|(unit, _)| {
+                *unit != projectile.shooter
+                    && units.get(*unit).is_ok_and(|target| target.health.0 > 0.0)
+            })
+            .filter_map(|(unit, unit_pos)| {
+                let offset = unit_pos - start;
+                let along = offset.dot(direction).clamp(0.0, length);
+
+                if (direction * along).distance(offset) <= projectile.hit_radius {
+                    Some((unit, along))
+                } else {
+                    None
+                }
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let obstacle = spatial
+            .ray_hits(
+                start,
+                direction,
+                length,
+                OBSTACLE_HITS,
+                true,
+                SpatialQueryFilter::default().without_entities([projectile.shooter]),
+            )
+            .into_iter()
+            .filter(|hit| terrain.contains(hit.entity))
+            .map(|hit| hit.time_of_impact)
+            .min_by(|a, b| a.total_cmp(b));
+
+        // Obstacles stop projectiles before they reach the units behind them
+        if let Some(obstacle) = obstacle {
+            let blocks = match hit {
+                None => true,
+                Some((_, along)) => obstacle < along,
+            };
+
+            if blocks {
+                commands.entity(ent).despawn_recursive();
+                continue;
+            }
+        }
+
+        if let Some((target, _)) = hit {
+            if let Ok(mut target_unit) = units.get_mut(target) {
+                let amount = deal_damage(
+                    &mut commands,
+                    &mut death_events,
+                    target,
+                    &mut target_unit,
+                    projectile.damage,
+                    projectile.velocity.normalize_or_zero(),
+                );
+
+                damage_events.send(DamageEvent {
+                    target,
+                    amount,
+                    critical: projectile.damage.critical,
+                });
+            }
+
+            if let (Some(on_hit), Ok(mut status)) = (on_hit, statuses.get_mut(target)) {
+                on_hit.apply_to(&mut status);
+            }
+
+            commands.entity(ent).despawn_recursive();
+            continue;
+        }
+
+        transform.translation += step.extend(0.0);
+        projectile.remaining -= length;
+
+        if projectile.remaining <= 0.0 {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
+
+pub fn despawn_projectiles(mut commands: Commands, projectiles: Query<Entity, With<Projectile>>) {
+    for ent in projectiles.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+use super::boss::SlamEvent;
+
+/// Seconds a shockwave takes to spread out and fade.
+const LIFETIME: f32 = 0.4;
+/// Radius of the shockwave image at a scale of 1.
+const IMAGE_RADIUS: f32 = 16.0;
+
+/// Ring spreading out from a boss's slam.
+#[derive(Component)]
+pub struct Shockwave {
+    age: f32,
+    radius: f32,
+}
+
+pub fn spawn_shockwaves(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut events: EventReader<SlamEvent>,
+) {
+    for event in events.read() {
+        commands.spawn((
+            Shockwave {
+                age: 0.0,
+                radius: event.radius,
+            },
+            SpriteBundle {
+                texture: asset_server.load("images/units/Shockwave.png"),
+                transform: Transform::from_translation(event.position.extend(5.0))
+                    .with_scale(Vec3::ZERO),
+                ..default()
+            },
+        ));
+    }
+}
+
+pub fn animate_shockwaves(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut shockwaves: Query<(Entity, &mut Shockwave, &mut Transform, &mut Sprite)>,
+) {
+    for (ent, mut shockwave, mut transform, mut sprite) in shockwaves.iter_mut() {
+        shockwave.age += time.delta_seconds();
+
+        if shockwave.age >= LIFETIME {
+            commands.entity(ent).despawn();
+            continue;
+        }
+
+        let t = shockwave.age / LIFETIME;
+
+        transform.scale = Vec3::splat(t * shockwave.radius / IMAGE_RADIUS);
+        sprite.color.set_a(1.0 - t);
+    }
+}
+
+pub fn despawn_shockwaves(mut commands: Commands, shockwaves: Query<Entity, With<Shockwave>>) {
+    for ent in shockwaves.iter() {
+        commands.entity(ent).despawn();
+    }
+}
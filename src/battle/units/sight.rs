@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use super::{
+    ai::{AttackRange, AttackTarget, Dead},
+    projectile::RangedAttack,
+    squad::Unit,
+    Team,
+};
+
+/// Whether projectiles hurt units of the team that fired them.
+/// Without it they pass through allies, but units still won't shoot through them.
+#[derive(Resource, Clone, Copy, Default)]
+pub struct FriendlyFire(pub bool);
+
+/// A ranged unit whose shot at its target would hit something else first.
+#[derive(Component, PartialEq)]
+pub struct BlockedShot {
+    /// Center of what is in the way, to step around it
+    pub obstacle: Vec2,
+}
+
+/// First thing a shot from `from` towards `target` would run into, if not `target` itself.
+/// Enemies in the way don't count, a shot that hits them isn't wasted.
+pub fn first_obstacle(
+    spatial: &SpatialQuery,
+    units: &Query<&Team, (With<Unit>, Without<Dead>)>,
+    shooter: Entity,
+    team: &Team,
+    from: Vec2,
+    target: Entity,
+    target_pos: Vec2,
+) -> Option<Entity> {
+    let offset = target_pos - from;
+    let distance = offset.length();
+
+    if distance <= 0.0 {
+        return None;
+    }
+
+    let hit = spatial.cast_ray(
+        from,
+        offset / distance,
+        distance,
+        true,
+        SpatialQueryFilter::default().without_entities([shooter]),
+    )?;
+
+    if hit.entity == target {
+        return None;
+    }
+
+    match units.get(hit.entity) {
+        Ok(hit_team) if hit_team != team => None,
+        _ => Some(hit.entity),
+    }
+}
+
+/// Marks ranged units in range of their target that can't get a clear shot at it.
+pub fn update_line_of_sight(
+    mut commands: Commands,
+    spatial: SpatialQuery,
+    mut shooters: Query<
+        (
+            Entity,
+            &Team,
+            &GlobalTransform,
+            &AttackRange,
+            Option<&AttackTarget>,
+            Option<&mut BlockedShot>,
+        ),
+        (With<RangedAttack>, Without<Dead>),
+    >,
+    units: Query<&Team, (With<Unit>, Without<Dead>)>,
+    transforms: Query<&GlobalTransform>,
+) {
+    for (ent, team, transform, range, target, blocked) in shooters.iter_mut() {
+        let pos = transform.translation().truncate();
+
+        let obstacle = target.and_then(|target| {
+            let target_pos = transforms.get(target.0).ok()?.translation().truncate();
+
+            // Out of range the unit is still closing in, which may clear the way by itself
+            if pos.distance(target_pos) > range.0 {
+                return None;
+            }
+
+            let obstacle = first_obstacle(&spatial, &units, ent, team, pos, target.0, target_pos)?;
+
+            Some(BlockedShot {
+                obstacle: transforms.get(obstacle).ok()?.translation().truncate(),
+            })
+        });
+
+        match (obstacle, blocked) {
+            (Some(obstacle), Some(mut blocked)) => {
+                blocked.set_if_neq(obstacle);
+            }
+            (Some(obstacle), None) => {
+                commands.entity(ent).insert(obstacle);
+            }
+            (None, Some(_)) => {
+                commands.entity(ent).remove::<BlockedShot>();
+            }
+            (None, None) => {}
+        }
+    }
+}
@@ -1,46 +1,89 @@
 use bevy::{
     audio::{PlaybackMode, VolumeLevel},
     prelude::*,
+    utils::HashMap,
+};
+
+use super::{
+    ai::{AttackEvent, DeathEvent},
+    catalog::{UnitCatalog, UnitDefinitions},
+    squad::UnitType,
 };
 
 pub struct SoundsPlugin;
 
 impl Plugin for SoundsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<SwingSound>()
-            .add_event::<DeathSound>()
-            .add_systems(Startup, load_sounds)
-            .add_systems(Update, (play_swing_sounds, play_death_sounds));
+        app.init_resource::<Sounds>().add_systems(
+            Update,
+            (reload_sounds, (play_attack_sounds, play_death_sounds)).chain(),
+        );
     }
 }
 
-#[derive(Resource)]
-pub struct Sounds {
-    pub swing: Handle<AudioSource>,
+/// Sounds for each unit type, loaded from the unit catalog as needed.
+#[derive(Resource, Default)]
+pub struct Sounds(HashMap<UnitType, UnitSounds>);
+
+#[derive(Clone)]
+pub struct UnitSounds {
+    pub attack: Handle<AudioSource>,
     pub death: Handle<AudioSource>,
 }
 
-fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(Sounds {
-        swing: asset_server.load("sounds/swing.ogg"),
-        death: asset_server.load("sounds/death.ogg"),
-    });
-}
+impl Sounds {
+    fn get(
+        &mut self,
+        unit: &UnitType,
+        definitions: &UnitDefinitions,
+        asset_server: &AssetServer,
+    ) -> Option<UnitSounds> {
+        if let Some(sounds) = self.0.get(unit) {
+            return Some(sounds.clone());
+        }
 
-#[derive(Event, Default)]
-pub struct SwingSound;
+        let definition = &definitions.get(unit)?.sounds;
 
-#[derive(Event, Default)]
-pub struct DeathSound;
+        let sounds = UnitSounds {
+            attack: asset_server.load(&definition.attack),
+            death: asset_server.load(&definition.death),
+        };
+
+        self.0.insert(unit.clone(), sounds.clone());
+
+        Some(sounds)
+    }
+}
 
-fn play_swing_sounds(
+fn reload_sounds(mut events: EventReader<AssetEvent<UnitCatalog>>, mut sounds: ResMut<Sounds>) {
+    for event in events.read() {
+        if let AssetEvent::Modified { .. } = event {
+            sounds.0.clear();
+        }
+    }
+}
+
+fn play_attack_sounds(
     mut commands: Commands,
-    sounds: Res<Sounds>,
-    mut swing: EventReader<SwingSound>,
+    mut sounds: ResMut<Sounds>,
+    asset_server: Res<AssetServer>,
+    definitions: UnitDefinitions,
+    mut attacks: EventReader<AttackEvent>,
+    units: Query<&UnitType>,
 ) {
-    for _ in swing.read() {
+    for event in attacks.read() {
+        let sounds = match units.get(event.attacker) {
+            Ok(unit) => sounds.get(unit, &definitions, &asset_server),
+            Err(_) => None,
+        };
+
+        let sounds = match sounds {
+            Some(sounds) => sounds,
+            None => continue,
+        };
+
         commands.spawn(AudioBundle {
-            source: sounds.swing.clone(),
+            source: sounds.attack,
             settings: PlaybackSettings {
                 volume: bevy::audio::Volume::Relative(VolumeLevel::new(0.5)),
                 mode: PlaybackMode::Despawn,
@@ -52,12 +95,25 @@ fn play_swing_sounds(
 
 fn play_death_sounds(
     mut commands: Commands,
-    sounds: Res<Sounds>,
-    mut death: EventReader<DeathSound>,
+    mut sounds: ResMut<Sounds>,
+    asset_server: Res<AssetServer>,
+    definitions: UnitDefinitions,
+    mut deaths: EventReader<DeathEvent>,
+    units: Query<&UnitType>,
 ) {
-    for _ in death.read() {
+    for event in deaths.read() {
+        let sounds = match units.get(event.unit) {
+            Ok(unit) => sounds.get(unit, &definitions, &asset_server),
+            Err(_) => None,
+        };
+
+        let sounds = match sounds {
+            Some(sounds) => sounds,
+            None => continue,
+        };
+
         commands.spawn(AudioBundle {
-            source: sounds.death.clone(),
+            source: sounds.death,
             settings: PlaybackSettings {
                 volume: bevy::audio::Volume::Relative(VolumeLevel::new(0.7)),
                 mode: PlaybackMode::Despawn,
@@ -0,0 +1,209 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{ai::Dead, squad::Unit, Team};
+
+/// Width and height of a grid cell, in world units.
+const CELL_SIZE: f32 = 32.0;
+
+/// Living units bucketed into a uniform grid for fast proximity lookups.
+/// Rebuilt every frame by [`update_spatial_grid`].
+#[derive(Resource, Default)]
+pub struct SpatialGrid {
+    teams: HashMap<Team, TeamGrid>,
+}
+
+struct TeamGrid {
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+    /// Bounds of the occupied cells, inclusive
+    min: IVec2,
+    max: IVec2,
+    len: usize,
+}
+
+impl Default for TeamGrid {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::default(),
+            min: IVec2::MAX,
+            max: IVec2::MIN,
+            len: 0,
+        }
+    }
+}
+
+fn cell(pos: Vec2) -> IVec2 {
+    (pos / CELL_SIZE).floor().as_ivec2()
+}
+
+impl TeamGrid {
+    fn clear(&mut self) {
+        // Keep the allocations around for the next frame
+        for units in self.cells.values_mut() {
+            units.clear();
+        }
+
+        self.min = IVec2::MAX;
+        self.max = IVec2::MIN;
+        self.len = 0;
+    }
+
+    fn insert(&mut self, ent: Entity, pos: Vec2) {
+        let cell = cell(pos);
+
+        self.cells.entry(cell).or_default().push((ent, pos));
+        self.min = self.min.min(cell);
+        self.max = self.max.max(cell);
+        self.len += 1;
+    }
+
+    /// Searches rings of cells around `pos`, moving outwards until no closer unit can exist.
+    fn nearest(&self, pos: Vec2, filter: impl Fn(Entity) -> bool) -> Option<(Entity, Vec2, f32)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let center = cell(pos);
+        let max_ring = (center - self.min)
+            .abs()
+            .max((self.max - center).abs())
+            .max_element();
+        // Rings closer than the occupied cells are empty
+        let min_ring = (self.min - center)
+            .max(center - self.max)
+            .max(IVec2::ZERO)
+            .max_element();
+
+        let mut best: Option<(Entity, Vec2, f32)> = None;
+
+        for ring in min_ring..=max_ring {
+            // Every unit in this ring is at least this far away
+            let min_distance = (ring - 1).max(0) as f32 * CELL_SIZE;
+
+            if let Some((_, _, distance)) = best {
+                if distance <= min_distance {
+                    break;
+                }
+            }
+
+            for_each_ring_cell(center, ring, self.min, self.max, |cell| {
+                let units = match self.cells.get(&cell) {
+                    Some(units) => units,
+                    None => return,
+                };
+
+                for (ent, unit_pos) in units.iter().filter(|(ent, _)| filter(*ent)) {
+                    let distance = pos.distance(*unit_pos);
+
+                    match best {
+                        Some((_, _, best)) if best <= distance => {}
+                        _ => best = Some((*ent, *unit_pos, distance)),
+                    }
+                }
+            });
+        }
+
+        best
+    }
+
+    fn within(&self, pos: Vec2, radius: f32) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let min = cell(pos - Vec2::splat(radius)).max(self.min);
+        let max = cell(pos + Vec2::splat(radius)).min(self.max);
+
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flat_map(|units| units.iter().copied())
+            .filter(move |(_, unit_pos)| unit_pos.distance(pos) <= radius)
+    }
+}
+
+/// Calls `f` for each cell at exactly `ring` cells (Chebyshev distance) from `center`,
+/// skipping cells outside of `min` and `max`.
+fn for_each_ring_cell(center: IVec2, ring: i32, min: IVec2, max: IVec2, mut f: impl FnMut(IVec2)) {
+    if ring == 0 {
+        f(center);
+        return;
+    }
+
+    let (min, max) = (min - center, max - center);
+    let contains = |offset: i32, min: i32, max: i32| min <= offset && offset <= max;
+
+    for x in (-ring).max(min.x)..=ring.min(max.x) {
+        for y in [ring, -ring] {
+            if contains(y, min.y, max.y) {
+                f(center + IVec2::new(x, y));
+            }
+        }
+    }
+
+    for y in (-ring + 1).max(min.y)..=(ring - 1).min(max.y) {
+        for x in [ring, -ring] {
+            if contains(x, min.x, max.x) {
+                f(center + IVec2::new(x, y));
+            }
+        }
+    }
+}
+
+impl SpatialGrid {
+    /// The closest living unit not on `team`, with its position and distance.
+    pub fn nearest_enemy(&self, team: &Team, pos: Vec2) -> Option<(Entity, Vec2, f32)> {
+        self.teams
+            .iter()
+            .filter(|(t, _)| *t != team)
+            .filter_map(|(_, grid)| grid.nearest(pos, |_| true))
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+    }
+
+    /// Living units not on `team` within `radius` of `pos`.
+    pub fn enemies_within<'a>(
+        &'a self,
+        team: &'a Team,
+        pos: Vec2,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + 'a {
+        self.teams
+            .iter()
+            .filter(move |(t, _)| *t != team)
+            .flat_map(move |(_, grid)| grid.within(pos, radius))
+    }
+
+    /// The closest living unit on `team`, other than `exclude`.
+    pub fn nearest_ally(
+        &self,
+        team: &Team,
+        pos: Vec2,
+        exclude: Entity,
+    ) -> Option<(Entity, Vec2, f32)> {
+        self.teams.get(team)?.nearest(pos, |ent| ent != exclude)
+    }
+
+    /// Living units on `team` within `radius` of `pos`.
+    pub fn allies_within<'a>(
+        &'a self,
+        team: &Team,
+        pos: Vec2,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + 'a {
+        self.teams
+            .get(team)
+            .into_iter()
+            .flat_map(move |grid| grid.within(pos, radius))
+    }
+}
+
+pub fn update_spatial_grid(
+    mut grid: ResMut<SpatialGrid>,
+    units: Query<(Entity, &Team, &GlobalTransform), (With<Unit>, Without<Dead>)>,
+) {
+    for team_grid in grid.teams.values_mut() {
+        team_grid.clear();
+    }
+
+    for (ent, team, transform) in units.iter() {
+        grid.teams
+            .entry(team.clone())
+            .or_default()
+            .insert(ent, transform.translation().truncate());
+    }
+}
@@ -1,61 +1,70 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 
 use super::{
+    ai::Dead,
+    catalog::{UnitCatalog, UnitDefinitions},
+    projectile::Projectile,
     squad::{Unit, UnitType},
+    status::StatusEffects,
     Team,
 };
 
+/// Texture atlases for each unit type, built from the unit catalog as needed.
 #[derive(Default, Resource)]
 pub struct UnitSprites {
-    archer_enemy: Handle<Image>,
-    archer_friendly: Handle<Image>,
-    knight_enemy: Handle<Image>,
-    knight_friendly: Handle<Image>,
+    atlases: HashMap<(UnitType, Team), Handle<TextureAtlas>>,
 }
 
-pub fn load_sprites(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(UnitSprites {
-        archer_enemy: asset_server.load("images/units/KnightEnemy.png"),
-        archer_friendly: asset_server.load("images/units/KnightFriendly.png"),
-        knight_enemy: asset_server.load("images/units/KnightEnemy.png"),
-        knight_friendly: asset_server.load("images/units/KnightFriendly.png"),
-    });
+pub fn reload_sprites(
+    mut events: EventReader<AssetEvent<UnitCatalog>>,
+    mut sprites: ResMut<UnitSprites>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified { .. } = event {
+            sprites.atlases.clear();
+        }
+    }
 }
 
 pub fn spawn_sprites(
     mut commands: Commands,
-    sprites: Res<UnitSprites>,
+    mut sprites: ResMut<UnitSprites>,
+    asset_server: Res<AssetServer>,
+    definitions: UnitDefinitions,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut units: Query<
         (Entity, &UnitType, &Team, &Transform),
-        (With<Unit>, Without<TextureAtlasSprite>),
+        (With<Unit>, Without<TextureAtlasSprite>, Without<Dead>),
     >,
 ) {
     for (ent, unit, team, transform) in units.iter_mut() {
-        let atlas = match unit {
-            UnitType::Archer => texture_atlases.add(TextureAtlas::from_grid(
-                match team {
-                    Team::Player => sprites.archer_friendly.clone(),
-                    Team::Enemy => sprites.archer_enemy.clone(),
-                },
-                UnitType::Archer.sprite_size(),
-                3,
-                1,
-                None,
-                None,
-            )),
+        let key = (unit.clone(), team.clone());
+
+        let atlas = match sprites.atlases.get(&key) {
+            Some(atlas) => atlas.clone(),
+            None => {
+                let definition = match definitions.get(unit) {
+                    Some(definition) => &definition.sprite,
+                    None => continue,
+                };
 
-            UnitType::Knight => texture_atlases.add(TextureAtlas::from_grid(
-                match team {
-                    Team::Player => sprites.knight_friendly.clone(),
-                    Team::Enemy => sprites.knight_enemy.clone(),
-                },
-                UnitType::Knight.sprite_size(),
-                3,
-                1,
-                None,
-                None,
-            )),
+                let image = match team {
+                    Team::Player => asset_server.load(&definition.friendly),
+                    Team::Enemy => asset_server.load(&definition.enemy),
+                };
+
+                let atlas = texture_atlases.add(TextureAtlas::from_grid(
+                    image,
+                    Vec2::from(definition.size),
+                    definition.frames,
+                    1,
+                    None,
+                    None,
+                ));
+
+                sprites.atlases.insert(key, atlas.clone());
+                atlas
+            }
         };
 
         let sprite = TextureAtlasSprite {
@@ -67,3 +76,53 @@ pub fn spawn_sprites(
         commands.entity(ent).insert((sprite, atlas));
     }
 }
+
+pub fn spawn_projectile_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    definitions: UnitDefinitions,
+    projectiles: Query<(Entity, &Projectile), Added<Projectile>>,
+) {
+    for (ent, projectile) in projectiles.iter() {
+        let sprite = match definitions
+            .get(&projectile.unit)
+            .and_then(|definition| definition.projectile.as_ref())
+        {
+            Some(definition) => &definition.sprite,
+            None => continue,
+        };
+
+        commands
+            .entity(ent)
+            .insert((Sprite::default(), asset_server.load::<Image>(sprite)));
+    }
+}
+
+/// Tints units by the status effect with the most time left.
+pub fn tint_status_effects(
+    mut units: Query<(&StatusEffects, &mut TextureAtlasSprite), Changed<StatusEffects>>,
+) {
+    for (status, mut sprite) in units.iter_mut() {
+        let color = match status
+            .0
+            .iter()
+            .max_by(|a, b| a.remaining.total_cmp(&b.remaining))
+        {
+            Some(active) => active.kind.tint(),
+            None => Color::WHITE,
+        };
+
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+pub fn hide_dead_units(mut commands: Commands, units: Query<Entity, (With<Unit>, Added<Dead>)>) {
+    for ent in units.iter() {
+        commands
+            .entity(ent)
+            .insert(Visibility::Hidden)
+            .remove::<TextureAtlasSprite>();
+    }
+}
@@ -1,9 +1,25 @@
-use bevy::prelude::*;
+use bevy::{
+    ecs::system::{EntityCommands, SystemParam},
+    prelude::*,
+};
 use rand::Rng;
-
-use crate::rewards::effects::FriendlyKnightSquadSizeModifier;
-
-use super::{formation::Formation, presets::UnitBundle, Team};
+use serde::{Deserialize, Serialize};
+
+use crate::rng::{RngStream, RunRng};
+
+use super::{
+    boss::Boss,
+    catalog::{UnitDefinition, UnitDefinitions},
+    formation::Formation,
+    modifiers::{SquadModifiers, Stat, StatModifiers},
+    morale::Morale,
+    orders::{FormationOffset, SquadState},
+    presets::UnitBundle,
+    projectile::RangedAttack,
+    status::ItemStatusEffects,
+    targeting::TargetingOverrides,
+    Team,
+};
 
 #[derive(Component)]
 pub struct Unit;
@@ -11,60 +27,155 @@ pub struct Unit;
 #[derive(Component, Clone, Default)]
 pub struct Squad;
 
-#[derive(Component, Clone, Default, Debug)]
-pub enum UnitType {
-    Archer,
-    #[default]
-    Knight,
-}
+/// Id of a unit type in the [`super::catalog::UnitCatalog`].
+#[derive(Component, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UnitType(pub String);
 
 impl UnitType {
-    pub fn spacing(&self) -> Vec2 {
-        match self {
-            UnitType::Knight => Vec2::splat(10.0),
-            UnitType::Archer => Vec2::splat(10.0),
-        }
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
     }
 
-    pub fn sprite_size(&self) -> Vec2 {
-        match self {
-            UnitType::Knight => Vec2::new(15.0, 8.0),
-            UnitType::Archer => Vec2::new(15.0, 8.0),
-        }
+    pub fn knight() -> Self {
+        Self::new("knight")
+    }
+
+    pub fn archer() -> Self {
+        Self::new("archer")
     }
 }
 
-#[derive(Component, Clone, Default)]
+impl Default for UnitType {
+    fn default() -> Self {
+        Self::knight()
+    }
+}
+
+#[derive(Component, Clone, Default, Deserialize)]
+#[serde(transparent)]
 pub struct SquadCount(pub usize);
 
-#[derive(Bundle, Clone, Default)]
+#[derive(Bundle, Clone, Default, Deserialize)]
 pub struct SquadBundle {
     pub count: SquadCount,
+    #[serde(default)]
     pub formation: Formation,
+    #[serde(skip)]
     pub squad: Squad,
     pub unit: UnitType,
 }
 
+/// Everything needed to spawn units with their team's upgrades applied.
+#[derive(SystemParam)]
+pub struct UnitSpawner<'w> {
+    pub definitions: UnitDefinitions<'w>,
+    pub modifiers: Res<'w, StatModifiers>,
+    status_effects: Res<'w, ItemStatusEffects>,
+    targeting: Res<'w, TargetingOverrides>,
+}
+
+impl<'w> UnitSpawner<'w> {
+    /// A unit type's definition with upgrades applied.
+    pub fn definition(
+        &self,
+        unit: &UnitType,
+        team: &Team,
+        squad_modifiers: Option<&SquadModifiers>,
+    ) -> Option<UnitDefinition> {
+        let definition = self.definitions.get(unit)?;
+
+        Some(
+            self.modifiers
+                .apply(definition, team, unit, squad_modifiers),
+        )
+    }
+
+    /// Spawns a single unit at `pos`, relative to its parent if it's given one.
+    pub fn spawn<'a, 'cw, 'cs>(
+        &self,
+        commands: &'a mut Commands<'cw, 'cs>,
+        definition: &UnitDefinition,
+        team: &Team,
+        unit: &UnitType,
+        pos: Vec2,
+    ) -> EntityCommands<'cw, 'cs, 'a> {
+        let mut unit_ent = commands.spawn((
+            Unit,
+            TransformBundle::from_transform(Transform::from_translation(pos.extend(0.0))),
+            VisibilityBundle::default(),
+            team.clone(),
+            unit.clone(),
+            UnitBundle::new(definition),
+        ));
+
+        if let Some(projectile) = &definition.projectile {
+            unit_ent.insert(RangedAttack::from(projectile));
+        }
+
+        if let Some(boss) = &definition.boss {
+            unit_ent.insert(Boss::new(boss));
+        }
+
+        let on_hit = self.status_effects.on_hit(&definition.on_hit, team, unit);
+
+        if !on_hit.0.is_empty() {
+            unit_ent.insert(on_hit);
+        }
+
+        unit_ent.insert((
+            self.status_effects.on_spawn(team, unit),
+            self.targeting.resolve(&definition.targeting, team, unit),
+        ));
+
+        unit_ent
+    }
+}
+
 pub fn spawn_units(
     mut commands: Commands,
-    mut squads: Query<(Entity, &Formation, &Team, &SquadCount, &UnitType), With<Squad>>,
-    friendly_squad_size_modifier: Res<FriendlyKnightSquadSizeModifier>,
-    enemy_squad_size_modifier: Res<FriendlyKnightSquadSizeModifier>,
+    mut squads: Query<
+        (
+            Entity,
+            &Formation,
+            &Team,
+            &SquadCount,
+            &UnitType,
+            Option<&SquadModifiers>,
+        ),
+        With<Squad>,
+    >,
+    spawner: UnitSpawner,
+    mut rng: ResMut<RunRng>,
 ) {
-    let mut rng = rand::thread_rng();
-
-    for (ent, formation, team, count, unit) in squads.iter_mut() {
-        let modifier = match team {
-            Team::Player => &friendly_squad_size_modifier.0,
-            Team::Enemy => &enemy_squad_size_modifier.0,
+    let rng = rng.stream(RngStream::UnitJitter);
+
+    for (ent, formation, team, count, unit, squad_modifiers) in squads.iter_mut() {
+        let definition = match spawner.definition(unit, team, squad_modifiers) {
+            Some(definition) => definition,
+            None => {
+                error!("Unknown unit type: {:?}", unit);
+                continue;
+            }
         };
 
-        let count = (count.0 as f32 * modifier.0) as usize;
+        let count =
+            spawner
+                .modifiers
+                .resolve(Stat::SquadSize, count.0 as f32, team, unit, squad_modifiers)
+                as usize;
         let coords = formation.coords(count);
 
+        commands.entity(ent).insert(SquadState::new(count));
+
+        // Bosses fight to the end
+        if definition.boss.is_none() {
+            commands.entity(ent).insert(Morale::default());
+        }
+
         for (mut x, mut y) in coords {
-            x *= unit.spacing().x;
-            y *= unit.spacing().y;
+            x *= definition.spacing.0;
+            y *= definition.spacing.1;
 
             x += rng.gen_range(-1.0..=1.0);
             y += rng.gen_range(-1.0..=1.0);
@@ -74,20 +185,11 @@ pub fn spawn_units(
                 Team::Enemy => x,
             };
 
-            let unit_bundle = match unit {
-                UnitType::Knight => UnitBundle::knight(),
-                UnitType::Archer => UnitBundle::archer(),
-            };
+            let pos = Vec2::new(x, y);
 
-            commands
-                .spawn((
-                    Unit,
-                    TransformBundle::from_transform(Transform::from_xyz(x, y, 0.0)),
-                    VisibilityBundle::default(),
-                    team.clone(),
-                    unit.clone(),
-                    unit_bundle,
-                ))
+            spawner
+                .spawn(&mut commands, &definition, team, unit, pos)
+                .insert(FormationOffset(pos))
                 .set_parent(ent);
         }
     }
@@ -0,0 +1,282 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::data::Validate;
+
+use super::{
+    ai::{Dead, DeathEvent, MaxHealth},
+    damage::{deal_damage, Damage, DamageTarget, DamageType},
+    squad::UnitType,
+    Team,
+};
+
+/// Longest a unit can be kept stunned by stacking stuns.
+const MAX_STUN: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// Damage per second, per stack
+    Poison,
+    /// Fraction of movement speed lost
+    Slow,
+    /// Can't move or attack
+    Stun,
+    /// Damage per second
+    Burn,
+    /// Health restored per second, per stack
+    Regeneration,
+}
+
+/// What happens when an effect is applied to a unit that already has it.
+pub enum Stacking {
+    /// Adds a stack up to a limit and refreshes the duration
+    Intensity { max_stacks: u32 },
+    /// Keeps the strongest potency and refreshes the duration
+    Refresh,
+    /// Adds to the remaining duration, up to a limit
+    Duration { max: f32 },
+}
+
+impl StatusKind {
+    pub fn stacking(&self) -> Stacking {
+        match self {
+            StatusKind::Poison => Stacking::Intensity { max_stacks: 5 },
+            StatusKind::Regeneration => Stacking::Intensity { max_stacks: 3 },
+            StatusKind::Burn | StatusKind::Slow => Stacking::Refresh,
+            StatusKind::Stun => Stacking::Duration { max: MAX_STUN },
+        }
+    }
+
+    pub fn tint(&self) -> Color {
+        match self {
+            StatusKind::Poison => Color::rgb(0.6, 1.0, 0.5),
+            StatusKind::Slow => Color::rgb(0.6, 0.8, 1.0),
+            StatusKind::Stun => Color::rgb(1.0, 1.0, 0.5),
+            StatusKind::Burn => Color::rgb(1.0, 0.6, 0.4),
+            StatusKind::Regeneration => Color::rgb(1.0, 0.7, 0.9),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    #[serde(default)]
+    pub potency: f32,
+    /// Seconds the effect lasts
+    pub duration: f32,
+}
+
+impl Validate for StatusEffect {
+    fn validate(&self) -> Result<(), String> {
+        if self.duration <= 0.0 {
+            return Err(format!(
+                "duration must be greater than 0, got {}",
+                self.duration
+            ));
+        }
+
+        if self.potency < 0.0 {
+            return Err(format!(
+                "potency must not be negative, got {}",
+                self.potency
+            ));
+        }
+
+        if self.kind == StatusKind::Slow && self.potency > 1.0 {
+            return Err(format!(
+                "slow potency must be at most 1, got {}",
+                self.potency
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ActiveStatus {
+    pub kind: StatusKind,
+    pub potency: f32,
+    pub remaining: f32,
+    pub stacks: u32,
+}
+
+/// Effects currently affecting a unit.
+#[derive(Component, Clone, Default)]
+pub struct StatusEffects(pub Vec<ActiveStatus>);
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect: &StatusEffect) {
+        let active = match self.0.iter_mut().find(|active| active.kind == effect.kind) {
+            Some(active) => active,
+            None => {
+                self.0.push(ActiveStatus {
+                    kind: effect.kind,
+                    potency: effect.potency,
+                    remaining: effect.duration,
+                    stacks: 1,
+                });
+                return;
+            }
+        };
+
+        match effect.kind.stacking() {
+            Stacking::Intensity { max_stacks } => {
+                active.stacks = (active.stacks + 1).min(max_stacks);
+                active.potency = active.potency.max(effect.potency);
+                active.remaining = active.remaining.max(effect.duration);
+            }
+            Stacking::Refresh => {
+                active.potency = active.potency.max(effect.potency);
+                active.remaining = active.remaining.max(effect.duration);
+            }
+            Stacking::Duration { max } => {
+                active.remaining = (active.remaining + effect.duration).min(max);
+            }
+        }
+    }
+
+    pub fn get(&self, kind: StatusKind) -> Option<&ActiveStatus> {
+        self.0.iter().find(|active| active.kind == kind)
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.get(StatusKind::Stun).is_some()
+    }
+
+    /// Multiplier for movement speed.
+    pub fn speed_factor(&self) -> f32 {
+        if self.is_stunned() {
+            return 0.0;
+        }
+
+        match self.get(StatusKind::Slow) {
+            Some(slow) => (1.0 - slow.potency).clamp(0.0, 1.0),
+            None => 1.0,
+        }
+    }
+}
+
+/// Effects a unit applies to whoever its attacks hit.
+#[derive(Component, Clone, Default)]
+pub struct OnHit(pub Vec<StatusEffect>);
+
+impl OnHit {
+    pub fn apply_to(&self, status: &mut StatusEffects) {
+        for effect in self.0.iter() {
+            status.apply(effect);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusTrigger {
+    /// Applied to units hit by an attack
+    OnHit,
+    /// Applied to the unit itself when it spawns
+    OnSpawn,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScopedStatusEffect {
+    pub team: Team,
+    pub unit: Option<UnitType>,
+    pub trigger: StatusTrigger,
+    pub effect: StatusEffect,
+}
+
+/// Status effects granted by items, for a team or only units of one type.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemStatusEffects(pub Vec<ScopedStatusEffect>);
+
+impl ItemStatusEffects {
+    pub fn get<'a>(
+        &'a self,
+        trigger: StatusTrigger,
+        team: &'a Team,
+        unit: &'a UnitType,
+    ) -> impl Iterator<Item = &'a StatusEffect> {
+        self.0
+            .iter()
+            .filter(move |scoped| scoped.trigger == trigger && scoped.team == *team)
+            .filter(move |scoped| match &scoped.unit {
+                Some(scoped_unit) => scoped_unit == unit,
+                None => true,
+            })
+            .map(|scoped| &scoped.effect)
+    }
+
+    /// On-hit effects of a unit, from its definition and items.
+    pub fn on_hit(&self, base: &[StatusEffect], team: &Team, unit: &UnitType) -> OnHit {
+        OnHit(
+            base.iter()
+                .chain(self.get(StatusTrigger::OnHit, team, unit))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Status a unit starts the battle with.
+    pub fn on_spawn(&self, team: &Team, unit: &UnitType) -> StatusEffects {
+        let mut status = StatusEffects::default();
+
+        for effect in self.get(StatusTrigger::OnSpawn, team, unit) {
+            status.apply(effect);
+        }
+
+        status
+    }
+}
+
+pub fn tick_status_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut units: Query<(Entity, &mut StatusEffects, &MaxHealth, DamageTarget), Without<Dead>>,
+) {
+    let delta = time.delta_seconds();
+
+    for (ent, mut status, max_health, mut target) in units.iter_mut() {
+        if status.0.is_empty() {
+            continue;
+        }
+
+        let mut damage = 0.0;
+
+        for active in status.0.iter_mut() {
+            let amount = active.potency * active.stacks as f32 * delta;
+
+            match active.kind {
+                StatusKind::Poison | StatusKind::Burn => damage += amount,
+                StatusKind::Regeneration => {
+                    target.health.0 = (target.health.0 + amount).min(max_health.0);
+                }
+                StatusKind::Slow | StatusKind::Stun => {}
+            }
+
+            active.remaining -= delta;
+        }
+
+        status.0.retain(|active| active.remaining > 0.0);
+
+        if damage > 0.0 {
+            // Effects over time bypass armor
+            deal_damage(
+                &mut commands,
+                &mut death_events,
+                ent,
+                &mut target,
+                Damage {
+                    amount: damage,
+                    damage_type: DamageType::Magic,
+                    penetration: 0.0,
+                    knockback: 0.0,
+                    critical: false,
+                },
+                Vec2::ZERO,
+            );
+        }
+    }
+}
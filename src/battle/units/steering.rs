@@ -0,0 +1,90 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{pathfinding::NavGrid, spatial::SpatialGrid, Team};
+
+/// Distance within which units push away from their allies.
+const SEPARATION_RADIUS: f32 = 8.0;
+const SEPARATION_WEIGHT: f32 = 0.8;
+/// Distance from its place in formation at which a unit is pulled back the hardest.
+const COHESION_RADIUS: f32 = 20.0;
+const COHESION_WEIGHT: f32 = 0.6;
+/// Distance from its target at which a unit stops keeping formation and spreads out to fight.
+const COHESION_FADE: f32 = 40.0;
+const ALIGNMENT_WEIGHT: f32 = 0.3;
+/// How far ahead units look for obstacles.
+const AVOID_DISTANCE: f32 = 12.0;
+const AVOID_WEIGHT: f32 = 1.5;
+
+/// Pushes a unit away from allies that are too close.
+pub fn separation(grid: &SpatialGrid, ent: Entity, team: &Team, pos: Vec2) -> Vec2 {
+    grid.allies_within(team, pos, SEPARATION_RADIUS)
+        .filter(|(other, _)| *other != ent)
+        .map(|(_, other_pos)| {
+            let away = pos - other_pos;
+            let distance = away.length();
+
+            if distance <= f32::EPSILON {
+                return Vec2::ZERO;
+            }
+
+            // Closer allies push harder
+            away / distance * (1.0 - distance / SEPARATION_RADIUS)
+        })
+        .sum()
+}
+
+/// How much a unit still keeps formation, from 1 while its target is far away to 0 up close.
+pub fn formation_fade(target_distance: f32) -> f32 {
+    (target_distance / COHESION_FADE).clamp(0.0, 1.0)
+}
+
+/// Pulls a unit towards its place in the formation, while its target is still far away.
+pub fn cohesion(pos: Vec2, slot: Vec2, target_distance: f32) -> Vec2 {
+    let offset = slot - pos;
+
+    offset / COHESION_RADIUS.max(offset.length()) * formation_fade(target_distance)
+}
+
+/// Turns a unit towards the average heading of its squad.
+/// `headings` holds the summed heading and unit count of every squad.
+pub fn alignment(headings: &HashMap<Entity, (Vec2, usize)>, squad: Entity) -> Vec2 {
+    match headings.get(&squad) {
+        Some((sum, count)) if *count > 0 => *sum / *count as f32,
+        _ => Vec2::ZERO,
+    }
+}
+
+/// Turns a unit moving in `direction` aside from obstacles ahead of it.
+pub fn avoidance(nav_grid: &NavGrid, pos: Vec2, direction: Vec2) -> Vec2 {
+    if !nav_grid.is_blocked(pos + direction * AVOID_DISTANCE) {
+        return Vec2::ZERO;
+    }
+
+    // Go around whichever side is open, preferring the left
+    let left = direction.perp();
+
+    if !nav_grid.is_blocked(pos + (direction + left).normalize_or_zero() * AVOID_DISTANCE) {
+        left
+    } else if !nav_grid.is_blocked(pos + (direction - left).normalize_or_zero() * AVOID_DISTANCE) {
+        -left
+    } else {
+        Vec2::ZERO
+    }
+}
+
+/// Combines the direction a unit wants to go in with its steering forces.
+/// The result is no longer than 1, so units never move faster than their speed.
+pub fn blend(
+    direction: Vec2,
+    separation: Vec2,
+    cohesion: Vec2,
+    alignment: Vec2,
+    avoidance: Vec2,
+) -> Vec2 {
+    (direction
+        + separation * SEPARATION_WEIGHT
+        + cohesion * COHESION_WEIGHT
+        + alignment * ALIGNMENT_WEIGHT
+        + avoidance * AVOID_WEIGHT)
+        .clamp_length_max(1.0)
+}
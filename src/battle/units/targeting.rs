@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ai::{AttackDamage, Health},
+    spatial::SpatialGrid,
+    squad::UnitType,
+    Team,
+};
+
+/// How far units look for targets that aren't the nearest.
+pub const SEARCH_RADIUS: f32 = 150.0;
+
+/// How a unit picks which enemy to attack.
+/// Every strategy except [`TargetingStrategy::Nearest`] only considers enemies within
+/// [`SEARCH_RADIUS`], and falls back to the nearest enemy if there are none.
+#[derive(Component, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum TargetingStrategy {
+    #[default]
+    Nearest,
+    LowestHealth,
+    HighestDamage,
+    Farthest,
+    /// Picks a random enemy and sticks with it until it dies or gets away
+    Random,
+    /// The nearest enemy of the given type
+    Prefer(UnitType),
+}
+
+/// Stats of potential targets that strategies compare.
+pub type TargetStats<'w, 's, 'a> = Query<'w, 's, (&'a Health, &'a AttackDamage, &'a UnitType)>;
+
+impl TargetingStrategy {
+    pub fn choose(
+        &self,
+        grid: &SpatialGrid,
+        stats: &TargetStats,
+        team: &Team,
+        pos: Vec2,
+        current: Option<Entity>,
+        rng: &mut impl Rng,
+    ) -> Option<(Entity, Vec2)> {
+        let nearest = || {
+            grid.nearest_enemy(team, pos)
+                .map(|(ent, pos, _)| (ent, pos))
+        };
+
+        if *self == TargetingStrategy::Nearest {
+            return nearest();
+        }
+
+        let candidates = grid.enemies_within(team, pos, SEARCH_RADIUS);
+
+        let chosen = match self {
+            TargetingStrategy::Nearest => None,
+            TargetingStrategy::LowestHealth => candidates
+                .filter_map(|(ent, p)| Some((ent, p, stats.get(ent).ok()?.0 .0)))
+                .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+                .map(|(ent, p, _)| (ent, p)),
+            TargetingStrategy::HighestDamage => candidates
+                .filter_map(|(ent, p)| Some((ent, p, stats.get(ent).ok()?.1 .0)))
+                .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+                .map(|(ent, p, _)| (ent, p)),
+            TargetingStrategy::Farthest => {
+                candidates.max_by(|(_, a), (_, b)| a.distance(pos).total_cmp(&b.distance(pos)))
+            }
+            TargetingStrategy::Random => {
+                let candidates = candidates.collect::<Vec<_>>();
+
+                match candidates.iter().find(|(ent, _)| Some(*ent) == current) {
+                    Some(current) => Some(*current),
+                    None if candidates.is_empty() => None,
+                    None => Some(candidates[rng.gen_range(0..candidates.len())]),
+                }
+            }
+            TargetingStrategy::Prefer(unit) => candidates
+                .filter(|(ent, _)| match stats.get(*ent) {
+                    Ok((_, _, candidate)) => candidate == unit,
+                    Err(_) => false,
+                })
+                .min_by(|(_, a), (_, b)| a.distance(pos).total_cmp(&b.distance(pos))),
+        };
+
+        chosen.or_else(nearest)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScopedTargeting {
+    pub team: Team,
+    pub unit: Option<UnitType>,
+    pub strategy: TargetingStrategy,
+}
+
+/// Targeting strategies granted by items, replacing the ones from the unit catalog.
+/// Later entries take priority.
+#[derive(Resource, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TargetingOverrides(pub Vec<ScopedTargeting>);
+
+impl TargetingOverrides {
+    pub fn resolve(
+        &self,
+        base: &TargetingStrategy,
+        team: &Team,
+        unit: &UnitType,
+    ) -> TargetingStrategy {
+        self.0
+            .iter()
+            .rev()
+            .filter(|scoped| scoped.team == *team)
+            .find(|scoped| match &scoped.unit {
+                Some(scoped_unit) => scoped_unit == unit,
+                None => true,
+            })
+            .map(|scoped| &scoped.strategy)
+            .unwrap_or(base)
+            .clone()
+    }
+}
@@ -2,12 +2,24 @@ use bevy::prelude::*;
 
 use crate::{Floor, GameState};
 
-use super::units::{ai::Dead, squad::Unit, Team};
+use super::units::{
+    ai::{Dead, UnitRole},
+    morale::Fleeing,
+    squad::Unit,
+    Team,
+};
+
+/// Sent once a battle ends.
+/// `winner` is `None` if both teams were wiped out.
+#[derive(Event)]
+pub struct BattleOver {
+    pub winner: Option<Team>,
+}
 
 pub fn detect_victory(
     mut battle_started: Local<bool>,
-    units: Query<&Team, (With<Unit>, Without<Dead>)>,
-    mut next_state: ResMut<NextState<GameState>>,
+    units: Query<(&Team, &UnitRole), (With<Unit>, Without<Dead>, Without<Fleeing>)>,
+    mut battle_over: EventWriter<BattleOver>,
 ) {
     if !*battle_started {
         if units.iter().count() > 0 {
@@ -21,7 +33,8 @@ pub fn detect_victory(
     let mut player_alive = false;
     let mut enemy_alive = false;
 
-    for team in units.iter() {
+    // Healers can't win a battle on their own, and fleeing units have given up
+    for (team, _) in units.iter().filter(|(_, role)| **role != UnitRole::Healer) {
         match team {
             Team::Player => player_alive = true,
             Team::Enemy => enemy_alive = true,
@@ -32,20 +45,34 @@ pub fn detect_victory(
         return;
     }
 
-    if player_alive {
+    let winner = if player_alive {
         info!("Player wins!");
-        next_state.set(GameState::Victory);
+        Some(Team::Player)
     } else if enemy_alive {
         info!("Enemy wins!");
-        next_state.set(GameState::Defeat);
+        Some(Team::Enemy)
     } else {
         info!("Draw!");
-        next_state.set(GameState::Defeat);
-    }
+        None
+    };
+
+    battle_over.send(BattleOver { winner });
 
     *battle_started = false;
 }
 
+pub fn finish_battle(
+    mut battle_over: EventReader<BattleOver>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for event in battle_over.read() {
+        match event.winner {
+            Some(Team::Player) => next_state.set(GameState::Victory),
+            _ => next_state.set(GameState::Defeat),
+        }
+    }
+}
+
 pub fn increase_floor(mut floor: ResMut<Floor>) {
     floor.0 += 1;
 }
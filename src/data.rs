@@ -0,0 +1,77 @@
+use std::{fmt, marker::PhantomData};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+/// Checks loaded data for mistakes the file format can't catch.
+pub trait Validate {
+    fn validate(&self) -> Result<(), String>;
+}
+
+/// Loads RON files, rejecting any that fail [`Validate`].
+pub struct RonLoader<A> {
+    extensions: &'static [&'static str],
+    marker: PhantomData<fn() -> A>,
+}
+
+impl<A> RonLoader<A> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self {
+            extensions,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RonLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Invalid(String),
+}
+
+impl fmt::Display for RonLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonLoaderError::Io(e) => write!(f, "could not read file: {}", e),
+            RonLoaderError::Ron(e) => write!(f, "could not parse file: {}", e),
+            RonLoaderError::Invalid(e) => write!(f, "invalid data: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RonLoaderError {}
+
+impl<A: Asset + DeserializeOwned + Validate> AssetLoader for RonLoader<A> {
+    type Asset = A;
+    type Settings = ();
+    type Error = RonLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(RonLoaderError::Io)?;
+
+            let asset: A = ron::de::from_bytes(&bytes).map_err(RonLoaderError::Ron)?;
+            asset.validate().map_err(RonLoaderError::Invalid)?;
+
+            Ok(asset)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
@@ -0,0 +1,191 @@
+use bevy::{prelude::*, reflect::TypePath};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    battle::{units::sight::FriendlyFire, INITIAL_UNITS},
+    data::{RonLoader, Validate},
+    rewards::drafting::DraftStrategy,
+};
+
+/// Settings for the custom difficulty, so players can tune their own runs.
+const CUSTOM_PATH: &str = "data/custom.difficulty.ron";
+
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Difficulty>()
+            .init_asset::<DifficultySettings>()
+            .register_asset_loader(RonLoader::<DifficultySettings>::new(&["difficulty.ron"]))
+            .add_event::<CycleDifficulty>()
+            .add_systems(Startup, load_custom)
+            .add_systems(
+                Update,
+                (
+                    cycle_difficulty,
+                    sync_friendly_fire.run_if(resource_changed::<Difficulty>()),
+                ),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DifficultyLevel {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Custom,
+}
+
+impl DifficultyLevel {
+    fn next(&self) -> Self {
+        match self {
+            DifficultyLevel::Easy => DifficultyLevel::Normal,
+            DifficultyLevel::Normal => DifficultyLevel::Hard,
+            DifficultyLevel::Hard => DifficultyLevel::Custom,
+            DifficultyLevel::Custom => DifficultyLevel::Easy,
+        }
+    }
+
+    /// Settings for the level, `None` for [`DifficultyLevel::Custom`] which comes from a file.
+    fn preset(&self) -> Option<DifficultySettings> {
+        let settings = match self {
+            DifficultyLevel::Easy => DifficultySettings {
+                enemy_base_units: 4.0,
+                enemy_units_per_floor: 1.0,
+                enemy_item_strength: 0.35,
+                item_choices: 4,
+                initial_units: 12,
+                draft_strategy: DraftStrategy::Random,
+                friendly_fire: false,
+            },
+            DifficultyLevel::Normal => DifficultySettings::default(),
+            DifficultyLevel::Hard => DifficultySettings {
+                enemy_base_units: 6.0,
+                enemy_units_per_floor: 2.0,
+                enemy_item_strength: 0.75,
+                item_choices: 2,
+                initial_units: 8,
+                draft_strategy: DraftStrategy::CounterPick,
+                friendly_fire: true,
+            },
+            DifficultyLevel::Custom => return None,
+        };
+
+        Some(settings)
+    }
+}
+
+#[derive(Asset, TypePath, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DifficultySettings {
+    /// Average size of enemy squads on the first floor
+    pub enemy_base_units: f32,
+    /// How much bigger enemy squads get each floor
+    pub enemy_units_per_floor: f32,
+    /// Fraction of an item's effect the enemy gets when it takes the item
+    pub enemy_item_strength: f32,
+    /// Number of items the player chooses from after each victory
+    pub item_choices: usize,
+    /// Size of the player's first squad
+    pub initial_units: usize,
+    #[serde(default)]
+    pub draft_strategy: DraftStrategy,
+    /// Whether ranged units can hurt their own side
+    #[serde(default)]
+    pub friendly_fire: bool,
+}
+
+impl Default for DifficultySettings {
+    fn default() -> Self {
+        Self {
+            enemy_base_units: INITIAL_UNITS as f32 / 2.0,
+            enemy_units_per_floor: 1.5,
+            enemy_item_strength: 0.5,
+            item_choices: 3,
+            initial_units: INITIAL_UNITS,
+            draft_strategy: DraftStrategy::Greedy,
+            friendly_fire: false,
+        }
+    }
+}
+
+impl Validate for DifficultySettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.enemy_base_units < 1.0 {
+            return Err(format!(
+                "enemy_base_units must be at least 1, got {}",
+                self.enemy_base_units
+            ));
+        }
+
+        if self.enemy_units_per_floor < 0.0 {
+            return Err(format!(
+                "enemy_units_per_floor must not be negative, got {}",
+                self.enemy_units_per_floor
+            ));
+        }
+
+        if self.enemy_item_strength < 0.0 {
+            return Err(format!(
+                "enemy_item_strength must not be negative, got {}",
+                self.enemy_item_strength
+            ));
+        }
+
+        if self.item_choices == 0 || self.initial_units == 0 {
+            return Err("item_choices and initial_units must be at least 1".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Difficulty of the current run, chosen from the main menu.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Difficulty {
+    pub level: DifficultyLevel,
+    pub settings: DifficultySettings,
+}
+
+#[derive(Resource)]
+pub struct CustomDifficultyHandle(pub Handle<DifficultySettings>);
+
+fn load_custom(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CustomDifficultyHandle(asset_server.load(CUSTOM_PATH)));
+}
+
+/// Sent to move on to the next difficulty level.
+#[derive(Event, Default)]
+pub struct CycleDifficulty;
+
+fn cycle_difficulty(
+    mut events: EventReader<CycleDifficulty>,
+    mut difficulty: ResMut<Difficulty>,
+    custom: Option<Res<CustomDifficultyHandle>>,
+    settings: Res<Assets<DifficultySettings>>,
+) {
+    for _ in events.read() {
+        let mut level = difficulty.level.next();
+
+        let settings = match level.preset() {
+            Some(settings) => settings,
+            None => match custom.as_ref().and_then(|custom| settings.get(&custom.0)) {
+                Some(settings) => settings.clone(),
+                None => {
+                    warn!("Custom difficulty not loaded from {}", CUSTOM_PATH);
+                    level = level.next();
+                    level.preset().unwrap_or_default()
+                }
+            },
+        };
+
+        info!("Difficulty set to {:?}", level);
+
+        *difficulty = Difficulty { level, settings };
+    }
+}
+
+fn sync_friendly_fire(difficulty: Res<Difficulty>, mut friendly_fire: ResMut<FriendlyFire>) {
+    friendly_fire.0 = difficulty.settings.friendly_fire;
+}
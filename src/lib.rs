@@ -3,10 +3,14 @@ use bevy::{asset::AssetMetaCheck, core_pipeline::clear_color::ClearColorConfig,
 use bevy_round_ui::prelude::RoundUiPlugin;
 use bevy_xpbd_2d::{plugins::PhysicsPlugins, resources::Gravity};
 
-mod battle;
+pub mod battle;
+mod data;
+mod difficulty;
 mod menu;
 mod music;
 mod rewards;
+pub mod rng;
+mod save;
 
 pub fn start() {
     App::new()
@@ -17,9 +21,12 @@ pub fn start() {
             // PhysicsDebugPlugin::default(),
             RoundUiPlugin,
             battle::BattlePlugin,
+            difficulty::DifficultyPlugin,
             menu::MenuPlugin,
             music::MusicPlugin,
             rewards::RewardsPlugin,
+            rng::RngPlugin,
+            save::SavePlugin,
         ))
         .init_resource::<Floor>()
         .insert_resource(Gravity(Vec2::ZERO))
@@ -37,6 +44,8 @@ pub enum GameState {
     Battle,
     Victory,
     Defeat,
+    Replay,
+    Resume,
 }
 
 #[derive(Resource, Default)]
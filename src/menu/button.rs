@@ -1,7 +1,7 @@
 use bevy::{app::AppExit, prelude::*};
 use bevy_round_ui::prelude::{RoundUiBorder, RoundUiMaterial, RoundUiOffset};
 
-use crate::GameState;
+use crate::{difficulty::CycleDifficulty, GameState};
 
 use super::colors;
 
@@ -84,7 +84,11 @@ pub fn handle_interactions(
 #[derive(Component, Debug)]
 pub enum ButtonAction {
     Start,
+    Continue,
     Quit,
+    WatchReplay,
+    Fight,
+    CycleDifficulty,
 }
 
 #[derive(Component)]
@@ -111,10 +115,26 @@ pub fn defer_actions(
                     action: ButtonAction::Start,
                     time,
                 }),
+                ButtonAction::Continue => commands.spawn(DeferredAction {
+                    action: ButtonAction::Continue,
+                    time,
+                }),
                 ButtonAction::Quit => commands.spawn(DeferredAction {
                     action: ButtonAction::Quit,
                     time,
                 }),
+                ButtonAction::WatchReplay => commands.spawn(DeferredAction {
+                    action: ButtonAction::WatchReplay,
+                    time,
+                }),
+                ButtonAction::Fight => commands.spawn(DeferredAction {
+                    action: ButtonAction::Fight,
+                    time,
+                }),
+                ButtonAction::CycleDifficulty => commands.spawn(DeferredAction {
+                    action: ButtonAction::CycleDifficulty,
+                    time,
+                }),
             };
         }
     }
@@ -126,6 +146,7 @@ pub fn handle_actions(
     mut commands: Commands,
     mut app_exit_events: EventWriter<AppExit>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut cycle_difficulty_writer: EventWriter<CycleDifficulty>,
 ) {
     for (deferred, entity) in &mut actions.iter() {
         let now = time.elapsed().as_millis();
@@ -137,7 +158,11 @@ pub fn handle_actions(
 
         match deferred.action {
             ButtonAction::Start => next_state.set(GameState::InitBattle),
+            ButtonAction::Continue => next_state.set(GameState::Resume),
             ButtonAction::Quit => app_exit_events.send(AppExit),
+            ButtonAction::WatchReplay => next_state.set(GameState::Replay),
+            ButtonAction::Fight => next_state.set(GameState::Battle),
+            ButtonAction::CycleDifficulty => cycle_difficulty_writer.send_default(),
         }
 
         info!("Button action complete: {:?}", deferred.action);
@@ -4,7 +4,7 @@ use bevy_round_ui::{
     prelude::{RoundUiBorder, RoundUiMaterial},
 };
 
-use crate::GameState;
+use crate::{difficulty::Difficulty, save, GameState};
 
 use self::button::{ButtonAction, ButtonStyle, RoundButton};
 
@@ -30,6 +30,7 @@ impl Plugin for MenuPlugin {
                     button::handle_interactions,
                     sounds::play_hover_sounds,
                     sounds::play_select_sounds,
+                    update_difficulty_label.run_if(resource_changed::<Difficulty>()),
                 ),
             );
     }
@@ -38,11 +39,16 @@ impl Plugin for MenuPlugin {
 #[derive(Component)]
 struct Menu;
 
+/// Button showing the difficulty the next run will be played on.
+#[derive(Component)]
+struct DifficultyButton;
+
 pub fn setup(
     mut commands: Commands,
     button_style: Res<button::ButtonStyle>,
     mut materials: ResMut<Assets<RoundUiMaterial>>,
     asset_server: Res<AssetServer>,
+    difficulty: Res<Difficulty>,
 ) {
     let font = asset_server.load("font/vt323.ttf");
 
@@ -104,7 +110,24 @@ pub fn setup(
                     ));
                 });
 
+                if save::save_exists() {
+                    spawn_button(
+                        p,
+                        &button_style,
+                        "Continue",
+                        font.clone(),
+                        ButtonAction::Continue,
+                    );
+                }
+
                 spawn_button(p, &button_style, "Start", font.clone(), ButtonAction::Start);
+                spawn_button(
+                    p,
+                    &button_style,
+                    difficulty_label(&difficulty),
+                    font.clone(),
+                    (ButtonAction::CycleDifficulty, DifficultyButton),
+                );
                 spawn_button(p, &button_style, "Quit", font.clone(), ButtonAction::Quit);
             });
         });
@@ -150,6 +173,24 @@ pub fn spawn_button(
         .id()
 }
 
+fn difficulty_label(difficulty: &Difficulty) -> String {
+    format!("Difficulty: {:?}", difficulty.level)
+}
+
+fn update_difficulty_label(
+    difficulty: Res<Difficulty>,
+    buttons: Query<&Children, With<DifficultyButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    for children in buttons.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = difficulty_label(&difficulty);
+            }
+        }
+    }
+}
+
 fn cleanup(mut commands: Commands, menu: Query<Entity, With<Menu>>) {
     for ent in menu.iter() {
         commands.entity(ent).despawn_recursive();
@@ -12,6 +12,7 @@ impl Plugin for MusicPlugin {
         app.add_systems(Startup, load_music)
             .add_systems(OnEnter(GameState::Menu), play_downtime)
             .add_systems(OnEnter(GameState::InitBattle), play_intro)
+            .add_systems(OnEnter(GameState::Resume), play_intro)
             .add_systems(
                 OnEnter(GameState::Battle),
                 (unpause_battle, despawn_downtime),
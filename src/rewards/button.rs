@@ -2,7 +2,12 @@ use bevy::prelude::*;
 use bevy_round_ui::prelude::{RoundUiBorder, RoundUiMaterial, RoundUiOffset};
 
 use crate::{
-    battle::units::Team,
+    battle::units::{
+        modifiers::{Modifier, ScopedModifier},
+        status::ScopedStatusEffect,
+        targeting::ScopedTargeting,
+        Team,
+    },
     menu::{
         colors,
         sounds::{HoverSound, SelectSound},
@@ -11,7 +16,9 @@ use crate::{
 };
 
 use super::{
-    effects::{AddColumn, AddMovementSpeed, AddRow, AddSquad, ItemEffect, SquadSizeMultiplier},
+    effects::{
+        AddColumn, AddModifier, AddRow, AddSquad, AddStatusEffect, ItemEffect, SetTargeting,
+    },
     items::{ItemLevel, ItemMaxCopies},
 };
 
@@ -94,15 +101,17 @@ pub fn handle_interactions(
 #[derive(Component)]
 pub struct ItemSelect(pub Entity);
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_item_select(
     interaction_query: Query<(&Interaction, &ItemSelect), Changed<Interaction>>,
     mut next_state: ResMut<NextState<GameState>>,
     mut items: Query<(&Name, &mut ItemMaxCopies, &mut ItemLevel, &ItemEffect)>,
-    mut add_movement_writer: EventWriter<AddMovementSpeed>,
+    mut add_modifier_writer: EventWriter<AddModifier>,
+    mut add_status_writer: EventWriter<AddStatusEffect>,
+    mut set_targeting_writer: EventWriter<SetTargeting>,
     mut add_squad_writer: EventWriter<AddSquad>,
     mut add_column_writer: EventWriter<AddColumn>,
     mut add_row_writer: EventWriter<AddRow>,
-    mut squad_size_multiplier_writer: EventWriter<SquadSizeMultiplier>,
 ) {
     for (interaction, action) in &interaction_query {
         if *interaction == Interaction::Pressed {
@@ -125,11 +134,12 @@ pub fn handle_item_select(
             activate_item_effect(
                 effect,
                 Team::Player,
-                &mut add_movement_writer,
+                &mut add_modifier_writer,
+                &mut add_status_writer,
+                &mut set_targeting_writer,
                 &mut add_squad_writer,
                 &mut add_column_writer,
                 &mut add_row_writer,
-                &mut squad_size_multiplier_writer,
             );
 
             next_state.set(GameState::PreBattle);
@@ -137,32 +147,50 @@ pub fn handle_item_select(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn activate_item_effect(
     effect: &ItemEffect,
     team: Team,
-    add_movement_writer: &mut EventWriter<AddMovementSpeed>,
+    add_modifier_writer: &mut EventWriter<AddModifier>,
+    add_status_writer: &mut EventWriter<AddStatusEffect>,
+    set_targeting_writer: &mut EventWriter<SetTargeting>,
     add_squad_writer: &mut EventWriter<AddSquad>,
     add_column_writer: &mut EventWriter<AddColumn>,
     add_row_writer: &mut EventWriter<AddRow>,
-    squad_size_multiplier_writer: &mut EventWriter<SquadSizeMultiplier>,
 ) {
     match effect {
-        ItemEffect::AddMovementSpeed(speed) => {
-            add_movement_writer.send(AddMovementSpeed {
-                speed: *speed,
+        ItemEffect::Modifier { stat, op, unit } => {
+            add_modifier_writer.send(AddModifier(ScopedModifier {
                 team,
-            });
+                unit: unit.clone(),
+                modifier: Modifier {
+                    stat: *stat,
+                    op: *op,
+                },
+            }));
         }
-        ItemEffect::AddSquad(squad) => {
-            add_squad_writer.send(AddSquad {
-                squad: squad.clone(),
+        ItemEffect::Status {
+            effect,
+            trigger,
+            unit,
+        } => {
+            add_status_writer.send(AddStatusEffect(ScopedStatusEffect {
                 team,
-            });
+                unit: unit.clone(),
+                trigger: *trigger,
+                effect: effect.clone(),
+            }));
         }
-        ItemEffect::SquadSizeMultiplier { multiplier, unit } => {
-            squad_size_multiplier_writer.send(SquadSizeMultiplier {
-                multiplier: *multiplier,
+        ItemEffect::Targeting { strategy, unit } => {
+            set_targeting_writer.send(SetTargeting(ScopedTargeting {
+                team,
                 unit: unit.clone(),
+                strategy: strategy.clone(),
+            }));
+        }
+        ItemEffect::AddSquad(squad) => {
+            add_squad_writer.send(AddSquad {
+                squad: squad.clone(),
                 team,
             });
         }
@@ -1,9 +1,14 @@
 use bevy::prelude::*;
 use rand::Rng;
 
-use crate::battle::{
-    layout::SquadSlot,
-    units::{squad::Squad, Team},
+use crate::{
+    battle::{
+        boss::is_boss_floor,
+        layout::SquadSlot,
+        units::{squad::Squad, Team},
+    },
+    rng::{RngStream, RunRng},
+    Floor,
 };
 
 use super::items::{
@@ -51,10 +56,12 @@ pub fn set_item_choices(
         &ItemRequirements,
     )>,
     open_slots: Query<(&SquadSlot, &Team), Without<Squad>>,
+    floor: Res<Floor>,
+    mut rng: ResMut<RunRng>,
 ) {
-    for team in &[Team::Player, Team::Enemy] {
-        let mut rng = rand::thread_rng();
+    let rng = rng.stream(RngStream::ItemChoices);
 
+    for team in &[Team::Player, Team::Enemy] {
         let open_slots = open_slots.iter().filter(|(_, t)| **t == *team).count();
 
         // Create a weighted list valid item choices
@@ -91,6 +98,21 @@ pub fn set_item_choices(
 
         choices.clear();
 
+        // Beating a boss guarantees at least one epic or better reward
+        if *team == Team::Player && is_boss_floor(floor.0) {
+            let rare = weighted_items
+                .iter()
+                .enumerate()
+                .filter(|(_, choice)| choice.rarity >= ItemRarity::Epic)
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+
+            if !rare.is_empty() {
+                let index = rare[rng.gen_range(0..rare.len())];
+                choices.push(weighted_items.remove(index));
+            }
+        }
+
         // Randomly select items from the weighted list
         // Ensure unique items
         while choices.len() < num_choices.0 && !weighted_items.is_empty() {
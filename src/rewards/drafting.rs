@@ -0,0 +1,367 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::battle::{
+    enemy::ArmyCatalogs,
+    units::{
+        ai::UnitRole,
+        catalog::UnitDefinitions,
+        damage::{Armor, Damage, Resistances},
+        modifiers::{Modifier, ScopedModifier, SquadModifiers, Stat, StatModifiers},
+        squad::{Squad, SquadCount, UnitType},
+        status::{ItemStatusEffects, ScopedStatusEffect, StatusKind},
+        Team,
+    },
+};
+
+use super::{choices::ItemChoice, effects::ItemEffect};
+
+/// Rough length of a battle in seconds, to weigh effects over time against one-off stats.
+const BATTLE_LENGTH: f32 = 20.0;
+/// Rough distance units have to cover before they can attack, at the start of a battle.
+const BATTLE_DISTANCE: f32 = 150.0;
+/// A new row or column is worth this fraction of the army's power, for the squads it will hold.
+/// A drafted squad costs as much, for the slot it takes up.
+const SLOT_VALUE: f32 = 0.1;
+
+/// How the enemy picks its reward after each battle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DraftStrategy {
+    /// Any of the choices
+    Random,
+    /// Whatever makes the enemy army strongest
+    #[default]
+    Greedy,
+    /// Whatever makes the enemy army strongest against the player's army
+    CounterPick,
+}
+
+/// The parts of a unit that matter for how strong an army is.
+struct UnitProfile {
+    count: f32,
+    health: f32,
+    armor: Armor,
+    resistances: Resistances,
+    /// Average hit, with critical hits folded in
+    damage: Damage,
+    attacks_per_second: f32,
+    /// Damage per second from status effects, which ignores armor
+    status_damage: f32,
+    /// Health restored to allies per second
+    healing: f32,
+    /// Fraction of the battle spent fighting instead of closing in
+    uptime: f32,
+}
+
+impl UnitProfile {
+    /// Damage per second this unit deals to `target`.
+    fn damage_against(&self, target: Option<&UnitProfile>) -> f32 {
+        let hit = match target {
+            Some(target) => self
+                .damage
+                .against(Some(&target.armor), Some(&target.resistances)),
+            None => self.damage.amount,
+        };
+
+        hit * self.attacks_per_second + self.status_damage
+    }
+}
+
+/// An army summed up for comparing rewards.
+pub struct Army(Vec<UnitProfile>);
+
+impl Army {
+    /// Weighted average of `f` over every unit in the army.
+    fn average(&self, f: impl Fn(&UnitProfile) -> f32) -> Option<f32> {
+        let count = self.0.iter().map(|unit| unit.count).sum::<f32>();
+
+        if count <= 0.0 {
+            return None;
+        }
+
+        Some(self.0.iter().map(|unit| unit.count * f(unit)).sum::<f32>() / count)
+    }
+
+    /// Damage the army deals per second, after the opponent's defenses.
+    fn damage_per_second(&self, opponent: Option<&Army>) -> f32 {
+        self.0
+            .iter()
+            .map(|unit| {
+                let dps = opponent
+                    .and_then(|opponent| {
+                        opponent.average(|target| unit.damage_against(Some(target)))
+                    })
+                    .unwrap_or_else(|| unit.damage_against(None));
+
+                unit.count * dps * unit.uptime
+            })
+            .sum()
+    }
+
+    /// Damage the army can take, given the damage the opponent deals.
+    fn effective_health(&self, opponent: Option<&Army>) -> f32 {
+        let healing = self
+            .0
+            .iter()
+            .map(|unit| unit.count * unit.healing * unit.uptime * BATTLE_LENGTH)
+            .sum::<f32>();
+
+        let health = self
+            .0
+            .iter()
+            .map(|unit| {
+                // Fraction of the opponent's damage that gets through this unit's defenses
+                let taken = opponent
+                    .and_then(|opponent| {
+                        let total = opponent.average(|attacker| attacker.damage_against(None))?;
+                        let through =
+                            opponent.average(|attacker| attacker.damage_against(Some(unit)))?;
+
+                        if total > 0.0 {
+                            Some(through / total)
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or(1.0);
+
+                unit.count * unit.health / taken.max(f32::EPSILON)
+            })
+            .sum::<f32>();
+
+        health + healing
+    }
+
+    /// How strong the army is, optionally against a specific opponent.
+    pub fn power(&self, opponent: Option<&Army>) -> f32 {
+        self.damage_per_second(opponent) * self.effective_health(opponent)
+    }
+}
+
+/// Everything needed to size up the armies on the field.
+#[derive(SystemParam)]
+pub struct ArmyStats<'w, 's> {
+    definitions: UnitDefinitions<'w>,
+    armies: ArmyCatalogs<'w>,
+    modifiers: Res<'w, StatModifiers>,
+    status_effects: Res<'w, ItemStatusEffects>,
+    squads: Query<
+        'w,
+        's,
+        (
+            &'static Team,
+            &'static UnitType,
+            &'static SquadCount,
+            Option<&'static SquadModifiers>,
+        ),
+        With<Squad>,
+    >,
+}
+
+impl<'w, 's> ArmyStats<'w, 's> {
+    pub fn army(&self, team: &Team) -> Army {
+        self.army_with(team, &self.modifiers, &self.status_effects, None, 1.0)
+    }
+
+    /// The team's army with different upgrades, and optionally an extra squad.
+    /// `kept` is the fraction of units the squads already in the army keep.
+    fn army_with(
+        &self,
+        team: &Team,
+        modifiers: &StatModifiers,
+        status_effects: &ItemStatusEffects,
+        extra_squad: Option<(&UnitType, usize)>,
+        kept: f32,
+    ) -> Army {
+        let squads = self
+            .squads
+            .iter()
+            .filter(|(t, _, _, _)| *t == team)
+            .map(|(_, unit, count, squad_modifiers)| (unit, count.0 as f32 * kept, squad_modifiers))
+            .chain(extra_squad.map(|(unit, count)| (unit, count as f32, None)));
+
+        let units = squads
+            .filter_map(|(unit, count, squad_modifiers)| {
+                let definition = self.definitions.get(unit)?;
+                let definition = modifiers.apply(definition, team, unit, squad_modifiers);
+                let count = modifiers.resolve(Stat::SquadSize, count, team, unit, squad_modifiers);
+
+                let attacks_per_second = 1.0 / definition.cooldown.max(f32::EPSILON);
+                let crit_bonus = definition.crit_chance * (definition.crit_multiplier - 1.0);
+
+                let status_damage = status_effects
+                    .on_hit(&definition.on_hit, team, unit)
+                    .0
+                    .iter()
+                    .filter(|effect| matches!(effect.kind, StatusKind::Poison | StatusKind::Burn))
+                    // Attacking faster than the effect wears off keeps it up the whole time
+                    .map(|effect| effect.potency * (effect.duration * attacks_per_second).min(1.0))
+                    .sum::<f32>();
+
+                let regeneration = status_effects
+                    .on_spawn(team, unit)
+                    .get(StatusKind::Regeneration)
+                    .map(|active| active.potency * active.stacks as f32 * active.remaining)
+                    .unwrap_or(0.0);
+
+                // Faster units, and units that reach further, start fighting sooner
+                let approach = (BATTLE_DISTANCE - definition.range).max(0.0)
+                    / definition.speed.max(f32::EPSILON);
+
+                let (damage, healing) = match definition.role {
+                    UnitRole::Fighter => (definition.damage * (1.0 + crit_bonus), 0.0),
+                    UnitRole::Healer => (0.0, definition.damage * attacks_per_second),
+                };
+
+                Some(UnitProfile {
+                    count,
+                    health: definition.health + regeneration,
+                    armor: Armor(definition.armor),
+                    resistances: definition.resistances.clone(),
+                    damage: Damage {
+                        amount: damage,
+                        damage_type: definition.damage_type,
+                        penetration: definition.penetration,
+                        knockback: 0.0,
+                        critical: false,
+                    },
+                    attacks_per_second,
+                    status_damage: match definition.role {
+                        UnitRole::Fighter => status_damage,
+                        UnitRole::Healer => 0.0,
+                    },
+                    healing,
+                    uptime: (1.0 - approach / BATTLE_LENGTH).max(0.0),
+                })
+            })
+            .collect();
+
+        Army(units)
+    }
+
+    /// Power of the team's army once it has an item's effect.
+    /// `squad_size` is how many units a squad added by the item would have.
+    fn power_with(
+        &self,
+        team: &Team,
+        effect: &ItemEffect,
+        squad_size: usize,
+        opponent: Option<&Army>,
+    ) -> f32 {
+        let power = match effect {
+            ItemEffect::Modifier { stat, op, unit } => {
+                let mut modifiers = self.modifiers.clone();
+
+                modifiers.0.push(ScopedModifier {
+                    team: team.clone(),
+                    unit: unit.clone(),
+                    modifier: Modifier {
+                        stat: *stat,
+                        op: *op,
+                    },
+                });
+
+                self.army_with(team, &modifiers, &self.status_effects, None, 1.0)
+            }
+            ItemEffect::Status {
+                effect,
+                trigger,
+                unit,
+            } => {
+                let mut status_effects = self.status_effects.clone();
+
+                status_effects.0.push(ScopedStatusEffect {
+                    team: team.clone(),
+                    unit: unit.clone(),
+                    trigger: *trigger,
+                    effect: effect.clone(),
+                });
+
+                self.army_with(team, &self.modifiers, &status_effects, None, 1.0)
+            }
+            ItemEffect::AddSquad(squad) => self.army_with(
+                team,
+                &self.modifiers,
+                &self.status_effects,
+                Some((&squad.unit, squad_size)),
+                self.kept_with_squad(team, &squad.unit, squad_size),
+            ),
+            ItemEffect::AddColumn | ItemEffect::AddRow | ItemEffect::Targeting { .. } => {
+                self.army(team)
+            }
+        }
+        .power(opponent);
+
+        match effect {
+            ItemEffect::AddColumn | ItemEffect::AddRow => power * (1.0 + SLOT_VALUE),
+            ItemEffect::AddSquad(_) => power * (1.0 - SLOT_VALUE),
+            _ => power,
+        }
+    }
+
+    /// Fraction of its units the team's army keeps once it drafts a squad.
+    /// Drafted squads are paid for out of the budget the enemy's army is bought with,
+    /// so they replace units instead of coming on top of them.
+    fn kept_with_squad(&self, team: &Team, unit: &UnitType, squad_size: usize) -> f32 {
+        let catalog = match self.armies.catalog() {
+            Some(catalog) => catalog,
+            None => return 1.0,
+        };
+
+        let army_cost = self
+            .squads
+            .iter()
+            .filter(|(t, _, _, _)| *t == team)
+            .map(|(_, unit, count, _)| catalog.cost(unit) * count.0 as f32)
+            .sum::<f32>();
+
+        if army_cost <= 0.0 {
+            return 1.0;
+        }
+
+        (1.0 - catalog.cost(unit) * squad_size as f32 / army_cost).max(0.0)
+    }
+}
+
+impl DraftStrategy {
+    /// Picks one of `choices`, each paired with the effect it would have for the enemy.
+    pub fn pick(
+        &self,
+        choices: &[(&ItemChoice, ItemEffect)],
+        stats: &ArmyStats,
+        squad_size: usize,
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        if choices.is_empty() {
+            return None;
+        }
+
+        let opponent = match self {
+            DraftStrategy::Random => return Some(rng.gen_range(0..choices.len())),
+            DraftStrategy::Greedy => None,
+            DraftStrategy::CounterPick => Some(stats.army(&Team::Player)),
+        };
+
+        let scores = choices
+            .iter()
+            .map(|(_, effect)| {
+                stats.power_with(&Team::Enemy, effect, squad_size, opponent.as_ref())
+            })
+            .collect::<Vec<_>>();
+
+        for ((choice, _), score) in choices.iter().zip(scores.iter()) {
+            debug!("Enemy scored {} at {}", choice.name, score);
+        }
+
+        // The first of the best choices, so ties go the same way every time
+        scores
+            .iter()
+            .enumerate()
+            .fold(None, |best: Option<(usize, f32)>, (i, score)| match best {
+                Some((_, best_score)) if best_score >= *score => best,
+                _ => Some((i, *score)),
+            })
+            .map(|(i, _)| i)
+    }
+}
@@ -1,13 +1,20 @@
 use bevy::prelude::*;
 use rand::Rng;
-
-use crate::battle::{
-    layout::{slot_coords, EnemyUnlockedSlots, FriendlyUnlockedSlots, SquadSlot},
-    units::{
-        formation::rand_formation,
-        squad::{Squad, SquadBundle, UnitType},
-        Team,
+use serde::Deserialize;
+
+use crate::{
+    battle::{
+        layout::{slot_coords, EnemyUnlockedSlots, FriendlyUnlockedSlots, SquadSlot},
+        units::{
+            formation::rand_formation,
+            modifiers::{ModifierOp, ScopedModifier, Stat, StatModifiers},
+            squad::{Squad, SquadBundle, UnitType},
+            status::{ItemStatusEffects, ScopedStatusEffect, StatusEffect, StatusTrigger},
+            targeting::{ScopedTargeting, TargetingOverrides, TargetingStrategy},
+            Team,
+        },
     },
+    rng::{RngStream, RunRng},
 };
 
 pub struct EffectsPlugin;
@@ -17,44 +24,50 @@ impl Plugin for EffectsPlugin {
         app.add_event::<AddColumn>()
             .add_event::<AddRow>()
             .add_event::<AddSquad>()
-            .add_event::<AddMovementSpeed>()
-            .add_event::<SquadSizeMultiplier>()
+            .add_event::<AddModifier>()
+            .add_event::<AddStatusEffect>()
+            .add_event::<SetTargeting>()
             .add_systems(
                 Update,
                 (
                     add_column,
                     add_row,
                     add_squad,
-                    add_movement_speed,
-                    apply_squad_size_modifier,
+                    add_modifier,
+                    add_status_effect,
+                    set_targeting,
                 ),
             );
     }
 }
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Deserialize)]
 pub enum ItemEffect {
     AddColumn,
-    AddMovementSpeed(f32),
     AddRow,
     AddSquad(SquadBundle),
-    SquadSizeMultiplier { multiplier: f32, unit: UnitType },
-}
-
-pub struct SpeedModifier(pub f32);
-
-impl Default for SpeedModifier {
-    fn default() -> Self {
-        Self(1.0)
-    }
+    /// Modifies a stat of every unit on the team, or only units of the given type
+    Modifier {
+        stat: Stat,
+        op: ModifierOp,
+        #[serde(default)]
+        unit: Option<UnitType>,
+    },
+    /// Applies a status effect on hit or when units spawn
+    Status {
+        effect: StatusEffect,
+        trigger: StatusTrigger,
+        #[serde(default)]
+        unit: Option<UnitType>,
+    },
+    /// Changes how units pick their targets
+    Targeting {
+        strategy: TargetingStrategy,
+        #[serde(default)]
+        unit: Option<UnitType>,
+    },
 }
 
-#[derive(Resource, Default)]
-pub struct FriendlySpeedModifier(pub SpeedModifier);
-
-#[derive(Resource, Default)]
-pub struct EnemySpeedModifier(pub SpeedModifier);
-
 #[derive(Event)]
 pub struct AddColumn {
     pub team: Team,
@@ -72,44 +85,47 @@ pub struct AddSquad {
 }
 
 #[derive(Event)]
-pub struct AddMovementSpeed {
-    pub speed: f32,
-    pub team: Team,
-}
+pub struct AddModifier(pub ScopedModifier);
 
 #[derive(Event)]
-pub struct SquadSizeMultiplier {
-    pub multiplier: f32,
-    pub unit: UnitType,
-    pub team: Team,
-}
+pub struct AddStatusEffect(pub ScopedStatusEffect);
+
+#[derive(Event)]
+pub struct SetTargeting(pub ScopedTargeting);
 
-pub struct SquadSizeModifier(pub f32);
+fn add_modifier(mut events: EventReader<AddModifier>, mut modifiers: ResMut<StatModifiers>) {
+    for AddModifier(modifier) in events.read() {
+        info!(
+            "Adding {:?} modifier to {:?}",
+            modifier.modifier.stat, modifier.team
+        );
 
-impl Default for SquadSizeModifier {
-    fn default() -> Self {
-        Self(1.0)
+        modifiers.0.push(modifier.clone());
     }
 }
 
-#[derive(Resource, Default)]
-pub struct FriendlyKnightSquadSizeModifier(pub SquadSizeModifier);
+fn add_status_effect(
+    mut events: EventReader<AddStatusEffect>,
+    mut status_effects: ResMut<ItemStatusEffects>,
+) {
+    for AddStatusEffect(effect) in events.read() {
+        info!(
+            "Adding {:?} {:?} to {:?}",
+            effect.trigger, effect.effect.kind, effect.team
+        );
 
-#[derive(Resource, Default)]
-pub struct EnemyKnightSquadSizeModifier(pub SquadSizeModifier);
+        status_effects.0.push(effect.clone());
+    }
+}
 
-fn add_movement_speed(
-    mut events: EventReader<AddMovementSpeed>,
-    mut enemy_modifier: ResMut<EnemySpeedModifier>,
-    mut friendly_modifier: ResMut<FriendlySpeedModifier>,
-) {
-    for AddMovementSpeed { speed, team } in events.read() {
-        info!("Adding movement speed to {:?}", team);
+fn set_targeting(mut events: EventReader<SetTargeting>, mut overrides: ResMut<TargetingOverrides>) {
+    for SetTargeting(targeting) in events.read() {
+        info!(
+            "Setting {:?} targeting to {:?}",
+            targeting.team, targeting.strategy
+        );
 
-        match team {
-            Team::Player => friendly_modifier.0 .0 += speed,
-            Team::Enemy => enemy_modifier.0 .0 += speed,
-        };
+        overrides.0.push(targeting.clone());
     }
 }
 
@@ -117,6 +133,7 @@ fn add_squad(
     mut commands: Commands,
     mut events: EventReader<AddSquad>,
     open_slots: Query<(Entity, &Team), (With<SquadSlot>, Without<Squad>)>,
+    mut rng: ResMut<RunRng>,
 ) {
     // Wait for slots to be spawned
     if open_slots.iter().count() == 0 {
@@ -139,13 +156,13 @@ fn add_squad(
             continue;
         }
 
-        let mut rng = rand::thread_rng();
-        let slot = open_slots[rng.gen_range(0..count)];
+        let slot = open_slots[rng.stream(RngStream::SquadSlot).gen_range(0..count)];
+        let formation = rand_formation(rng.stream(RngStream::Formation));
 
         commands
             .entity(slot)
             .insert(squad.clone())
-            .insert(rand_formation());
+            .insert(formation);
     }
 }
 
@@ -222,29 +239,3 @@ fn add_row(
         }
     }
 }
-
-fn apply_squad_size_modifier(
-    mut events: EventReader<SquadSizeMultiplier>,
-    mut friendly_modifier: ResMut<FriendlyKnightSquadSizeModifier>,
-    mut enemy_modifier: ResMut<EnemyKnightSquadSizeModifier>,
-) {
-    for SquadSizeMultiplier {
-        multiplier,
-        unit,
-        team,
-    } in events.read()
-    {
-        info!("Applying squad size modifier to {:?}", team);
-
-        match team {
-            Team::Player => match unit {
-                UnitType::Knight => friendly_modifier.0 .0 *= multiplier,
-                _ => {}
-            },
-            Team::Enemy => match unit {
-                UnitType::Knight => enemy_modifier.0 .0 *= multiplier,
-                _ => {}
-            },
-        };
-    }
-}
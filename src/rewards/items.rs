@@ -1,14 +1,20 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, reflect::TypePath, utils::HashSet};
+use serde::Deserialize;
 
-use crate::battle::{
-    layout::{INITIAL_COLUMNS, MAX_COLUMNS},
-    units::squad::{SquadBundle, SquadCount, UnitType},
-    INITIAL_UNITS,
+use crate::{
+    battle::{
+        layout::{INITIAL_COLUMNS, INITIAL_ROWS, MAX_COLUMNS, MAX_ROWS},
+        units::modifiers::ModifierOp,
+    },
+    data::Validate,
 };
 
 use super::effects::ItemEffect;
 
-#[derive(Component, Clone, Default)]
+const CATALOG_PATH: &str = "data/base.items.ron";
+
+/// Ordered from most to least common.
+#[derive(Component, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
 pub enum ItemRarity {
     #[default]
     Common,
@@ -64,6 +70,7 @@ impl Default for ItemLevel {
 #[derive(Component, Default)]
 pub struct ItemDescription(pub String);
 
+#[derive(Clone, Deserialize)]
 pub enum ItemRequirement {
     OpenSlot,
 }
@@ -83,66 +90,202 @@ pub struct ItemBundle {
     requirements: ItemRequirements,
 }
 
-pub fn init_items(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn(ItemBundle {
-        copies: ItemMaxCopies(1),
-        description: ItemDescription("+25% movement speed".to_string()),
-        effect: ItemEffect::AddMovementSpeed(0.25),
-        image: asset_server.load("images/items/Coffee.png"),
-        level: ItemLevel::new(4),
-        name: Name::new("Coffee"),
-        rarity: ItemRarity::Rare,
-        requirements: ItemRequirements::default(),
-    });
+/// The reward pool.
+#[derive(Asset, TypePath, Deserialize)]
+#[serde(transparent)]
+pub struct ItemCatalog(pub Vec<ItemDefinition>);
 
-    commands.spawn(ItemBundle {
-        copies: ItemMaxCopies(15),
-        description: ItemDescription("+1 knight squad".to_string()),
-        effect: ItemEffect::AddSquad(SquadBundle {
-            unit: UnitType::Knight,
-            count: SquadCount(INITIAL_UNITS),
-            ..default()
-        }),
-        image: asset_server.load("images/items/KnightItem.png"),
-        name: Name::new("Knight Squad"),
-        level: ItemLevel::default(),
-        rarity: ItemRarity::Common,
-        requirements: ItemRequirements(vec![ItemRequirement::OpenSlot]),
-    });
+#[derive(Clone, Deserialize)]
+pub struct ItemDefinition {
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    pub rarity: ItemRarity,
+    /// Number of times the item can be picked at its max level
+    #[serde(default = "default_copies")]
+    pub copies: usize,
+    #[serde(default = "default_max_level")]
+    pub max_level: usize,
+    #[serde(default)]
+    pub requirements: Vec<ItemRequirement>,
+    pub effect: ItemEffect,
+}
 
-    commands.spawn(ItemBundle {
-        copies: ItemMaxCopies(1),
-        description: ItemDescription("+50% knight squad size".to_string()),
-        effect: ItemEffect::SquadSizeMultiplier {
-            multiplier: 1.5,
-            unit: UnitType::Knight,
-        },
-        image: asset_server.load("images/items/BallOfKnights.png"),
-        name: Name::new("Ball of Knights"),
-        level: ItemLevel::new(10),
-        rarity: ItemRarity::Epic,
-        requirements: ItemRequirements(vec![ItemRequirement::OpenSlot]),
-    });
+fn default_copies() -> usize {
+    ItemMaxCopies::default().0
+}
 
-    commands.spawn(ItemBundle {
-        copies: ItemMaxCopies(MAX_COLUMNS - INITIAL_COLUMNS),
-        description: ItemDescription("+1 column".to_string()),
-        effect: ItemEffect::AddColumn,
-        image: asset_server.load("images/items/AddColumn.png"),
-        name: Name::new("Column"),
-        level: ItemLevel::default(),
-        rarity: ItemRarity::Epic,
-        requirements: ItemRequirements::default(),
-    });
+fn default_max_level() -> usize {
+    ItemLevel::default().max_level
+}
+
+/// Item names show the level in roman numerals, which only go up to X.
+const MAX_LEVEL: usize = 10;
+
+impl Validate for ItemCatalog {
+    fn validate(&self) -> Result<(), String> {
+        if self.0.is_empty() {
+            return Err("no items defined".to_string());
+        }
+
+        let mut names = HashSet::new();
+
+        for item in self.0.iter() {
+            if !names.insert(item.name.as_str()) {
+                return Err(format!("item \"{}\" is defined more than once", item.name));
+            }
+
+            item.validate()
+                .map_err(|e| format!("item \"{}\": {}", item.name, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Validate for ItemDefinition {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("name must not be empty".to_string());
+        }
+
+        if self.copies == 0 {
+            return Err("copies must be at least 1".to_string());
+        }
+
+        if self.max_level == 0 || self.max_level > MAX_LEVEL {
+            return Err(format!(
+                "max_level must be between 1 and {}, got {}",
+                MAX_LEVEL, self.max_level
+            ));
+        }
+
+        match &self.effect {
+            ItemEffect::AddColumn if self.copies > MAX_COLUMNS - INITIAL_COLUMNS => Err(format!(
+                "copies must be at most {}, the number of locked columns",
+                MAX_COLUMNS - INITIAL_COLUMNS
+            )),
+            ItemEffect::AddRow if self.copies > MAX_ROWS - INITIAL_ROWS => Err(format!(
+                "copies must be at most {}, the number of locked rows",
+                MAX_ROWS - INITIAL_ROWS
+            )),
+            ItemEffect::AddSquad(squad) if squad.count.0 == 0 => {
+                Err("squad count must be at least 1".to_string())
+            }
+            ItemEffect::AddSquad(_)
+                if !self
+                    .requirements
+                    .iter()
+                    .any(|req| matches!(req, ItemRequirement::OpenSlot)) =>
+            {
+                Err("adding a squad requires the OpenSlot requirement".to_string())
+            }
+            ItemEffect::Modifier {
+                op: ModifierOp::Multiply(multiplier),
+                ..
+            } if *multiplier <= 0.0 => Err(format!(
+                "multiplier must be greater than 0, got {}",
+                multiplier
+            )),
+            ItemEffect::Status { effect, .. } => effect.validate(),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ItemCatalogHandle(pub Handle<ItemCatalog>);
+
+pub fn load_items(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ItemCatalogHandle(asset_server.load(CATALOG_PATH)));
+}
 
-    commands.spawn(ItemBundle {
-        copies: ItemMaxCopies(1),
-        description: ItemDescription("+1 row".to_string()),
-        effect: ItemEffect::AddRow,
-        image: asset_server.load("images/items/AddRow.png"),
-        name: Name::new("Row"),
-        level: ItemLevel::default(),
-        rarity: ItemRarity::Epic,
-        requirements: ItemRequirements::default(),
+/// Spawns an entity for each item in the catalog.
+/// When the catalog is reloaded, existing items are updated in place,
+/// keeping the levels and copies taken so far.
+pub fn sync_items(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<ItemCatalog>>,
+    asset_server: Res<AssetServer>,
+    handle: Res<ItemCatalogHandle>,
+    catalogs: Res<Assets<ItemCatalog>>,
+    mut items: Query<(
+        Entity,
+        &Name,
+        &mut ItemMaxCopies,
+        &mut ItemDescription,
+        &mut Handle<Image>,
+        &mut ItemLevel,
+        &mut ItemRarity,
+        &mut ItemEffect,
+        &mut ItemRequirements,
+    )>,
+) {
+    let changed = events.read().any(|event| match event {
+        AssetEvent::Added { id } | AssetEvent::Modified { id } => *id == handle.0.id(),
+        _ => false,
     });
+
+    if !changed {
+        return;
+    }
+
+    let catalog = match catalogs.get(&handle.0) {
+        Some(catalog) => catalog,
+        None => return,
+    };
+
+    info!("Loading {} items", catalog.0.len());
+
+    let mut existing = HashSet::new();
+
+    for (
+        ent,
+        name,
+        mut copies,
+        mut description,
+        mut image,
+        mut level,
+        mut rarity,
+        mut effect,
+        mut requirements,
+    ) in items.iter_mut()
+    {
+        let definition = match catalog.0.iter().find(|item| item.name == name.as_str()) {
+            Some(definition) => definition,
+            None => {
+                info!("Removing item: {}", name);
+                commands.entity(ent).despawn_recursive();
+                continue;
+            }
+        };
+
+        existing.insert(definition.name.clone());
+
+        copies.0 = copies.0.min(definition.copies);
+        description.0 = definition.description.clone();
+        *image = asset_server.load(&definition.icon);
+        level.max_level = definition.max_level;
+        level.level = level.level.min(definition.max_level);
+        *rarity = definition.rarity.clone();
+        *effect = definition.effect.clone();
+        requirements.0 = definition.requirements.clone();
+    }
+
+    for definition in catalog.0.iter() {
+        if existing.contains(&definition.name) {
+            continue;
+        }
+
+        commands.spawn(ItemBundle {
+            copies: ItemMaxCopies(definition.copies),
+            description: ItemDescription(definition.description.clone()),
+            effect: definition.effect.clone(),
+            image: asset_server.load(&definition.icon),
+            level: ItemLevel::new(definition.max_level),
+            name: Name::new(definition.name.clone()),
+            rarity: definition.rarity.clone(),
+            requirements: ItemRequirements(definition.requirements.clone()),
+        });
+    }
 }
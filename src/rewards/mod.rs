@@ -3,26 +3,36 @@ use bevy_round_ui::{
     autosize::{RoundUiAutosizeNode, RoundUiAutosizeNodePadding},
     prelude::{RoundUiBorder, RoundUiMaterial},
 };
-use rand::Rng;
 
 use crate::{
-    battle::{enemy::rand_unit_count, units::Team},
+    battle::{
+        enemy::{mean_unit_count, rand_unit_count, DraftedSquad, DraftedSquads},
+        units::{
+            modifiers::{Modifier, StatModifiers},
+            status::{ItemStatusEffects, StatusEffect},
+            targeting::TargetingOverrides,
+            Team,
+        },
+    },
+    data::RonLoader,
+    difficulty::Difficulty,
     menu::colors,
+    rng::{RngStream, RunRng},
     Floor, GameState,
 };
 
 use self::{
     button::{activate_item_effect, ItemCard, ItemCardStyle, ItemSelect},
     choices::{EnemyItemChoices, FriendlyItemChoices, ItemChoice, NumItemChoices},
+    drafting::ArmyStats,
     effects::{
-        AddColumn, AddMovementSpeed, AddRow, AddSquad, EnemyKnightSquadSizeModifier,
-        EnemySpeedModifier, FriendlyKnightSquadSizeModifier, FriendlySpeedModifier, ItemEffect,
-        SquadSizeMultiplier,
+        AddColumn, AddModifier, AddRow, AddSquad, AddStatusEffect, ItemEffect, SetTargeting,
     },
 };
 
 mod button;
 pub mod choices;
+pub mod drafting;
 pub mod effects;
 pub mod items;
 
@@ -31,19 +41,21 @@ pub struct RewardsPlugin;
 impl Plugin for RewardsPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(effects::EffectsPlugin)
+            .init_asset::<items::ItemCatalog>()
+            .register_asset_loader(RonLoader::<items::ItemCatalog>::new(&["items.ron"]))
             .init_resource::<EnemyItemChoices>()
-            .init_resource::<EnemySpeedModifier>()
             .init_resource::<FriendlyItemChoices>()
-            .init_resource::<FriendlySpeedModifier>()
-            .init_resource::<FriendlyKnightSquadSizeModifier>()
-            .init_resource::<EnemyKnightSquadSizeModifier>()
             .init_resource::<ItemCardStyle>()
             .init_resource::<NumItemChoices>()
-            .add_systems(Startup, items::init_items)
+            .add_systems(Startup, items::load_items)
             .add_systems(OnEnter(GameState::InitBattle), init_resources)
             .add_systems(
                 Update,
-                (button::handle_interactions, button::handle_item_select),
+                (
+                    button::handle_interactions,
+                    button::handle_item_select,
+                    items::sync_items,
+                ),
             )
             .add_systems(
                 OnEnter(GameState::Victory),
@@ -53,14 +65,45 @@ impl Plugin for RewardsPlugin {
     }
 }
 
-fn init_resources(mut commands: Commands) {
+fn init_resources(mut commands: Commands, difficulty: Res<Difficulty>) {
     commands.insert_resource(EnemyItemChoices::default());
-    commands.insert_resource(EnemySpeedModifier::default());
     commands.insert_resource(FriendlyItemChoices::default());
-    commands.insert_resource(FriendlySpeedModifier::default());
-    commands.insert_resource(FriendlyKnightSquadSizeModifier::default());
-    commands.insert_resource(EnemyKnightSquadSizeModifier::default());
-    commands.insert_resource(NumItemChoices::default());
+    commands.insert_resource(ItemStatusEffects::default());
+    commands.insert_resource(StatModifiers::default());
+    commands.insert_resource(TargetingOverrides::default());
+    commands.insert_resource(NumItemChoices(difficulty.settings.item_choices));
+}
+
+/// The effect an item has when the enemy takes it, scaled by `strength`.
+fn enemy_effect(effect: &ItemEffect, strength: f32) -> ItemEffect {
+    match effect {
+        ItemEffect::Modifier { stat, op, unit } => {
+            let modifier = Modifier {
+                stat: *stat,
+                op: *op,
+            }
+            .scaled(strength);
+
+            ItemEffect::Modifier {
+                stat: modifier.stat,
+                op: modifier.op,
+                unit: unit.clone(),
+            }
+        }
+        ItemEffect::Status {
+            effect,
+            trigger,
+            unit,
+        } => ItemEffect::Status {
+            effect: StatusEffect {
+                potency: effect.potency * strength,
+                ..effect.clone()
+            },
+            trigger: *trigger,
+            unit: unit.clone(),
+        },
+        _ => effect.clone(),
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -68,44 +111,71 @@ fn upgrade_enemy(
     choices: Res<EnemyItemChoices>,
     effects: Query<(Entity, &ItemEffect)>,
     floor: Res<Floor>,
-    mut add_movement_writer: EventWriter<AddMovementSpeed>,
+    difficulty: Res<Difficulty>,
+    stats: ArmyStats,
+    mut drafted: ResMut<DraftedSquads>,
+    mut add_modifier_writer: EventWriter<AddModifier>,
+    mut add_status_writer: EventWriter<AddStatusEffect>,
+    mut set_targeting_writer: EventWriter<SetTargeting>,
     mut add_squad_writer: EventWriter<AddSquad>,
     mut add_column_writer: EventWriter<AddColumn>,
     mut add_row_writer: EventWriter<AddRow>,
-    mut squad_size_multiplier_writer: EventWriter<SquadSizeMultiplier>,
+    mut rng: ResMut<RunRng>,
 ) {
-    let mut rng = rand::thread_rng();
+    let candidates = choices
+        .0
+        .iter()
+        .filter_map(|item| match effects.get(item.entity) {
+            Ok((_, effect)) => Some((
+                item,
+                enemy_effect(effect, difficulty.settings.enemy_item_strength),
+            )),
+            Err(_) => None,
+        })
+        .collect::<Vec<_>>();
 
-    // Pick a random item from the choices
-    let item = rng.gen_range(0..choices.0.len());
-    let item = &choices.0[item];
+    let squad_size = mean_unit_count(floor.0, &difficulty.settings).round() as usize;
+    let strategy = difficulty.settings.draft_strategy;
 
-    info!("Enemy chose item: {}", item.name);
-
-    let effect = effects.get(item.entity).unwrap().1;
-
-    let effect = match effect {
-        ItemEffect::AddMovementSpeed(speed) => ItemEffect::AddMovementSpeed(speed / 2.0),
-        ItemEffect::SquadSizeMultiplier { multiplier, unit } => ItemEffect::SquadSizeMultiplier {
-            multiplier: ((multiplier - 1.0) / 2.0) + 1.0,
-            unit: unit.clone(),
-        },
-        ItemEffect::AddSquad(squad) => {
-            let mut squad = squad.clone();
-            squad.count.0 = rand_unit_count(floor.0);
-            ItemEffect::AddSquad(squad)
+    let (item, effect) = match strategy.pick(
+        &candidates,
+        &stats,
+        squad_size,
+        rng.stream(RngStream::EnemyUpgrade),
+    ) {
+        Some(i) => &candidates[i],
+        None => {
+            error!("Enemy has no items to choose from");
+            return;
         }
-        _ => effect.clone(),
     };
 
+    info!("Enemy chose item: {} ({:?})", item.name, strategy);
+
+    // The enemy's army is bought anew each floor, so drafted squads are kept aside and added to it.
+    // They are scored at their average size, the actual size is rolled once picked
+    if let ItemEffect::AddSquad(squad) = effect {
+        drafted.0.push(DraftedSquad {
+            unit: squad.unit.clone(),
+            count: rand_unit_count(
+                floor.0,
+                &difficulty.settings,
+                rng.stream(RngStream::EnemyCount),
+            ),
+        });
+
+        return;
+    }
+
     activate_item_effect(
-        &effect,
+        effect,
         Team::Enemy,
-        &mut add_movement_writer,
+        &mut add_modifier_writer,
+        &mut add_status_writer,
+        &mut set_targeting_writer,
         &mut add_squad_writer,
         &mut add_column_writer,
         &mut add_row_writer,
-        &mut squad_size_multiplier_writer,
     );
 }
 
@@ -0,0 +1,103 @@
+use bevy::{prelude::*, utils::HashMap};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::GameState;
+
+/// Set this environment variable to replay a run from its seed.
+const SEED_ENV: &str = "TOWER_QUEST_SEED";
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunRng>()
+            .add_systems(OnEnter(GameState::InitBattle), new_run);
+    }
+}
+
+/// Each subsystem draws from its own stream, so extra draws in one
+/// don't shift the results of another.
+/// Streams are seeded from their discriminant, so new ones go at the end.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum RngStream {
+    EnemyCount,
+    EnemyUpgrade,
+    Formation,
+    ItemChoices,
+    SquadSlot,
+    UnitJitter,
+    ProjectileSpread,
+    Targeting,
+    SquadOrders,
+    DamageRoll,
+    Terrain,
+    Army,
+}
+
+/// Random number generator for an entire run, derived from a single seed.
+#[derive(Resource)]
+pub struct RunRng {
+    seed: u64,
+    streams: HashMap<RngStream, ChaCha12Rng>,
+}
+
+impl RunRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Continues a run's streams from where [`RunRng::positions`] left them.
+    pub fn resume(seed: u64, positions: &HashMap<RngStream, u64>) -> Self {
+        let mut rng = Self::new(seed);
+
+        for (stream, position) in positions.iter() {
+            rng.stream(*stream).set_word_pos(*position as u128);
+        }
+
+        rng
+    }
+
+    /// How far each stream that has been drawn from has gotten.
+    pub fn positions(&self) -> HashMap<RngStream, u64> {
+        self.streams
+            .iter()
+            .map(|(stream, rng)| (*stream, rng.get_word_pos() as u64))
+            .collect()
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha12Rng {
+        let seed = self.seed ^ (stream as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+
+        self.streams
+            .entry(stream)
+            .or_insert_with(|| ChaCha12Rng::seed_from_u64(seed))
+    }
+}
+
+impl Default for RunRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+/// Starts a new run. Systems in `OnEnter(GameState::InitBattle)` that draw
+/// from the [`RunRng`] should run after this.
+pub fn new_run(mut commands: Commands) {
+    let seed = std::env::var(SEED_ENV)
+        .ok()
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or_else(rand::random);
+
+    info!("Run seed: {}", seed);
+
+    commands.insert_resource(RunRng::new(seed));
+}
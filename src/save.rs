@@ -0,0 +1,286 @@
+use std::path::Path;
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    battle::{
+        enemy::{ArmyPending, DraftedSquads},
+        layout::{EnemyUnlockedSlots, FriendlyUnlockedSlots, SquadSlot, UnlockedSlots},
+        terrain::{ArenaPending, CurrentArena},
+        units::{
+            formation::Formation,
+            modifiers::{SquadModifiers, StatModifiers},
+            orders::SquadOrders,
+            squad::{Squad, SquadBundle, SquadCount, Unit, UnitType},
+            status::ItemStatusEffects,
+            targeting::TargetingOverrides,
+            Team,
+        },
+    },
+    difficulty::Difficulty,
+    rewards::{
+        choices::NumItemChoices,
+        items::{ItemLevel, ItemMaxCopies},
+    },
+    rng::{RngStream, RunRng},
+    Floor, GameState,
+};
+
+const SAVE_DIR: &str = "saves";
+const SAVE_PATH: &str = "saves/run.ron";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        // Saved once everything random about the floor has been drawn, before the battle
+        app.add_systems(OnExit(GameState::PreBattle), save_run)
+            .add_systems(OnEnter(GameState::InitBattle), delete_save)
+            .add_systems(OnEnter(GameState::Defeat), delete_save)
+            .add_systems(Update, resume_run.run_if(in_state(GameState::Resume)));
+    }
+}
+
+/// Everything needed to continue a run from the start of a floor.
+#[derive(Serialize, Deserialize)]
+struct SaveData {
+    seed: u64,
+    /// How far each random stream has gotten, so the run continues as if it was never stopped
+    #[serde(default)]
+    streams: HashMap<RngStream, u64>,
+    floor: usize,
+    /// Arena layout of the floor's battle
+    #[serde(default)]
+    arena: Option<String>,
+    #[serde(default)]
+    difficulty: Difficulty,
+    friendly_slots: SavedUnlockedSlots,
+    enemy_slots: SavedUnlockedSlots,
+    slots: Vec<SavedSlot>,
+    #[serde(default)]
+    modifiers: StatModifiers,
+    #[serde(default)]
+    status_effects: ItemStatusEffects,
+    #[serde(default)]
+    targeting: TargetingOverrides,
+    #[serde(default)]
+    drafted_squads: DraftedSquads,
+    items: Vec<SavedItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedUnlockedSlots {
+    rows: usize,
+    columns: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSlot {
+    team: Team,
+    position: [f32; 2],
+    squad: Option<SavedSquad>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSquad {
+    unit: UnitType,
+    count: usize,
+    formation: Formation,
+    #[serde(default)]
+    modifiers: SquadModifiers,
+    #[serde(default)]
+    orders: SquadOrders,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedItem {
+    name: String,
+    level: usize,
+    copies: usize,
+}
+
+pub fn save_exists() -> bool {
+    Path::new(SAVE_PATH).exists()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_run(
+    floor: Res<Floor>,
+    rng: Res<RunRng>,
+    arena: Res<CurrentArena>,
+    difficulty: Res<Difficulty>,
+    friendly_slots: Res<FriendlyUnlockedSlots>,
+    enemy_slots: Res<EnemyUnlockedSlots>,
+    modifiers: Res<StatModifiers>,
+    status_effects: Res<ItemStatusEffects>,
+    targeting: Res<TargetingOverrides>,
+    drafted_squads: Res<DraftedSquads>,
+    slots: Query<
+        (
+            &Team,
+            &Transform,
+            Option<(
+                &UnitType,
+                &SquadCount,
+                &Formation,
+                Option<&SquadModifiers>,
+                Option<&SquadOrders>,
+            )>,
+        ),
+        With<SquadSlot>,
+    >,
+    items: Query<(&Name, &ItemLevel, &ItemMaxCopies)>,
+) {
+    let save = SaveData {
+        seed: rng.seed(),
+        streams: rng.positions(),
+        floor: floor.0,
+        arena: arena.0.clone(),
+        difficulty: difficulty.clone(),
+        friendly_slots: SavedUnlockedSlots {
+            rows: friendly_slots.0.rows,
+            columns: friendly_slots.0.columns,
+        },
+        enemy_slots: SavedUnlockedSlots {
+            rows: enemy_slots.0.rows,
+            columns: enemy_slots.0.columns,
+        },
+        slots: slots
+            .iter()
+            .map(|(team, transform, squad)| SavedSlot {
+                team: team.clone(),
+                position: transform.translation.truncate().to_array(),
+                squad: squad.map(|(unit, count, formation, modifiers, orders)| SavedSquad {
+                    unit: unit.clone(),
+                    count: count.0,
+                    formation: formation.clone(),
+                    modifiers: modifiers.cloned().unwrap_or_default(),
+                    orders: orders.cloned().unwrap_or_default(),
+                }),
+            })
+            .collect(),
+        modifiers: modifiers.clone(),
+        status_effects: status_effects.clone(),
+        targeting: targeting.clone(),
+        drafted_squads: drafted_squads.clone(),
+        items: items
+            .iter()
+            .map(|(name, level, copies)| SavedItem {
+                name: name.to_string(),
+                level: level.level,
+                copies: copies.0,
+            })
+            .collect(),
+    };
+
+    let result = ron::ser::to_string_pretty(&save, default())
+        .map_err(|e| e.to_string())
+        .and_then(|save| {
+            std::fs::create_dir_all(SAVE_DIR)
+                .and_then(|_| std::fs::write(SAVE_PATH, save))
+                .map_err(|e| e.to_string())
+        });
+
+    match result {
+        Ok(_) => info!("Saved run on floor {}", floor.0),
+        Err(e) => error!("Failed to save run: {}", e),
+    }
+}
+
+fn delete_save() {
+    if save_exists() {
+        if let Err(e) = std::fs::remove_file(SAVE_PATH) {
+            error!("Failed to delete save: {}", e);
+        }
+    }
+}
+
+fn load_save() -> Result<SaveData, String> {
+    let save = std::fs::read_to_string(SAVE_PATH).map_err(|e| e.to_string())?;
+    ron::from_str(&save).map_err(|e| e.to_string())
+}
+
+fn resume_run(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    old_slots: Query<Entity, With<SquadSlot>>,
+    units: Query<Entity, With<Unit>>,
+    mut items: Query<(&Name, &mut ItemLevel, &mut ItemMaxCopies)>,
+) {
+    // Wait for the item catalog to load
+    if items.is_empty() {
+        return;
+    }
+
+    let save = match load_save() {
+        Ok(save) => save,
+        Err(e) => {
+            error!("Failed to load save: {}", e);
+            next_state.set(GameState::Menu);
+            return;
+        }
+    };
+
+    info!("Continuing run on floor {}", save.floor);
+
+    for ent in old_slots.iter().chain(units.iter()) {
+        commands.entity(ent).despawn_recursive();
+    }
+
+    for slot in save.slots {
+        let mut ent = commands.spawn((
+            slot.team,
+            SquadSlot,
+            TransformBundle {
+                local: Transform::from_xyz(slot.position[0], slot.position[1], 0.0),
+                ..default()
+            },
+            VisibilityBundle::default(),
+        ));
+
+        if let Some(squad) = slot.squad {
+            ent.insert((
+                SquadBundle {
+                    count: SquadCount(squad.count),
+                    formation: squad.formation,
+                    squad: Squad,
+                    unit: squad.unit,
+                },
+                squad.modifiers,
+                squad.orders,
+            ));
+        }
+    }
+
+    for (name, mut level, mut copies) in items.iter_mut() {
+        if let Some(item) = save.items.iter().find(|item| item.name == name.as_str()) {
+            level.level = item.level.min(level.max_level);
+            copies.0 = item.copies;
+        }
+    }
+
+    commands.insert_resource(Floor(save.floor));
+    commands.insert_resource(RunRng::resume(save.seed, &save.streams));
+    // Saves from before arenas were kept get a new one
+    commands.insert_resource(ArenaPending(save.arena.is_none()));
+    commands.insert_resource(CurrentArena(save.arena));
+    commands.insert_resource(NumItemChoices(save.difficulty.settings.item_choices));
+    commands.insert_resource(save.difficulty);
+    commands.insert_resource(FriendlyUnlockedSlots(UnlockedSlots {
+        rows: save.friendly_slots.rows,
+        columns: save.friendly_slots.columns,
+    }));
+    commands.insert_resource(EnemyUnlockedSlots(UnlockedSlots {
+        rows: save.enemy_slots.rows,
+        columns: save.enemy_slots.columns,
+    }));
+    commands.insert_resource(save.modifiers);
+    commands.insert_resource(save.status_effects);
+    commands.insert_resource(save.targeting);
+    commands.insert_resource(save.drafted_squads);
+    // The saved enemy army is the one to fight
+    commands.insert_resource(ArmyPending(false));
+
+    next_state.set(GameState::PreBattle);
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use tower_quest::{
+    battle::{
+        sim::{run_battle, BattleSimPlugin},
+        units::{
+            squad::{Squad, SquadBundle, SquadCount, UnitType},
+            Team,
+        },
+    },
+    rng::RunRng,
+};
+
+fn spawn_squads(mut commands: Commands) {
+    for (team, count, x) in [(Team::Player, 20, -60.0), (Team::Enemy, 2, 60.0)] {
+        commands.spawn((
+            SquadBundle {
+                count: SquadCount(count),
+                formation: default(),
+                squad: Squad,
+                unit: UnitType::new("knight"),
+            },
+            team,
+            TransformBundle::from_transform(Transform::from_xyz(x, 0.0, 0.0)),
+        ));
+    }
+}
+
+#[test]
+fn larger_squad_wins() {
+    let mut app = App::new();
+
+    app.add_plugins((MinimalPlugins, BattleSimPlugin))
+        .insert_resource(RunRng::new(0))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            1.0 / 60.0,
+        )))
+        .add_systems(Startup, spawn_squads);
+
+    let result = run_battle(&mut app);
+
+    assert_eq!(result.winner, Some(Team::Player));
+}